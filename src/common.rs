@@ -1,8 +1,14 @@
 use std::fmt;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use home::home_dir;
+use serde::Deserialize;
 
 // GENERAL ---------------------------------------------------------------------
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
 #[allow(dead_code)]
 pub enum TermStyle {
     Reset,
@@ -127,22 +133,41 @@ pub fn title(s: &str) -> String {
     format!("{:-^80}\n", s)
 }
 
-pub async fn parse_request_loose_json(
-    w: Result<reqwest::Response, reqwest::Error>,
-) -> Result<serde_json::Value, String> {
+// labels each location's block when a module shows more than one.
+pub fn location_heading(name: &str) -> String {
+    format!("{Bold}{name}:{Reset}\n")
+}
+
+pub async fn request_text(w: Result<reqwest::Response, reqwest::Error>) -> Result<String, String> {
     let r = w.map_err(|e| e.to_string())?;
-    let t = r.text().await.map_err(|e| e.to_string())?;
+    r.text().await.map_err(|e| e.to_string())
+}
+
+pub fn parse_loose_json(t: &str) -> Result<serde_json::Value, String> {
     // dbg!(&t);
-    let j = serde_json::from_str(&t).map_err(|e| e.to_string())?;
-    Ok(j)
+    serde_json::from_str(t).map_err(|e| e.to_string())
 }
 
 // CONFIG ----------------------------------------------------------------------
 
+// persistent files that aren't configuration, like the privacy audit log.
+pub fn data_dir() -> Result<PathBuf> {
+    let home_dir = home_dir().context("Could not find users home directory.")?;
+    Ok(home_dir.join(".local").join("share").join("unifetch"))
+}
+
+// things that can be thrown away, like which wxer mirror worked last.
+pub fn cache_dir() -> Result<PathBuf> {
+    let home_dir = home_dir().context("Could not find users home directory.")?;
+    Ok(home_dir.join(".cache").join("unifetch"))
+}
+
 // TODO: Do not hard code this.
 // const COORDS: (f64, f64) = DURHAM_COORDS;
 // const DURHAM_COORDS: (f64, f64) = (43.13, -70.92);
 
+// coordinates are already rounded by the localization config, don't add or
+// remove any precision here.
 pub fn coords_str(coords: (f32, f32)) -> String {
-    format!("{:.2},{:.2}", coords.0, coords.1)
+    format!("{},{}", coords.0, coords.1)
 }
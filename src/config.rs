@@ -1,15 +1,25 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Mutex;
 
 use super::Args;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use chrono_tz::Tz;
 use home::home_dir;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
+use crate::common;
+use crate::gazetteer;
+use crate::output::Output;
+use crate::privacy::AuditRecord;
 use crate::tides;
+use crate::timings::Timings;
+use crate::wx::alerts::AlertHit;
+use crate::wx::wxer::WxerHealth;
 
 // one of the stupidest functions I've ever written
 fn t() -> bool {
@@ -78,52 +88,405 @@ pub enum Modules {
     Updates,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Eq)]
+impl Modules {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Modules::DateTime => "datetime",
+            Modules::CurrentConditions => "conditions",
+            Modules::Forecast => "forecast",
+            Modules::Teleconnections => "teleconnections",
+            Modules::Earthquakes => "earthquakes",
+            Modules::Random => "random",
+            Modules::SolarLunar => "solarlunar",
+            Modules::Tides => "tides",
+            Modules::Updates => "updates",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum Service {
     Wxer,
     Usno,
     Usgs,
+    #[serde(alias = "open_meteo", alias = "open-meteo")]
+    OpenMeteo,
+}
+
+impl Service {
+    const ALL: [Service; 4] = [
+        Service::Wxer,
+        Service::Usno,
+        Service::Usgs,
+        Service::OpenMeteo,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Service::Wxer => "wxer",
+            Service::Usno => "usno",
+            Service::Usgs => "usgs",
+            Service::OpenMeteo => "openmeteo",
+        }
+    }
+
+    // matches what each module used to round to before precision was
+    // configurable.
+    fn default_precision(&self) -> Precision {
+        match self {
+            Service::Wxer => Precision::Exact,
+            Service::Usno => Precision::Decimals(2),
+            Service::Usgs => Precision::Decimals(0),
+            Service::OpenMeteo => Precision::Decimals(2),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PrecisionRepr {
+    Decimals(u8),
+    Keyword(String),
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(try_from = "PrecisionRepr")]
+pub enum Precision {
+    Exact,
+    Decimals(u8),
+}
+
+impl TryFrom<PrecisionRepr> for Precision {
+    type Error = String;
+
+    fn try_from(value: PrecisionRepr) -> Result<Self, Self::Error> {
+        match value {
+            // an f32 has nothing past this, and more overflows the rounding
+            PrecisionRepr::Decimals(d) if d > 6 => Err(format!(
+                "precision {d} is more than 6 decimals, use \"exact\" instead"
+            )),
+            PrecisionRepr::Decimals(d) => Ok(Precision::Decimals(d)),
+            PrecisionRepr::Keyword(k) if k == "exact" => Ok(Precision::Exact),
+            PrecisionRepr::Keyword(k) => Err(format!(
+                "unknown precision \"{k}\", expected a number of decimals or \"exact\""
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Precision::Exact => write!(f, "exact"),
+            Precision::Decimals(d) => write!(f, "{d} decimals"),
+        }
+    }
+}
+
+impl Precision {
+    fn apply(&self, value: f32) -> f32 {
+        match self {
+            Precision::Exact => value,
+            Precision::Decimals(d) => {
+                let factor = 10f32.powi(*d as i32);
+                (value * factor).round() / factor
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Location {
+    pub name: String,
+
+    latitude: Option<f32>,
+    longitude: Option<f32>,
+    altitude: Option<f32>,
+
+    // resolved offline into the fields above, which take priority if given.
+    place: Option<String>,
+    timezone: Option<String>,
+    #[serde(skip)]
+    tz: Option<Tz>,
+}
+
+impl Location {
+    fn resolve(&mut self, gazetteer: Option<&Path>) -> Result<()> {
+        if let Some(query) = &self.place {
+            let place = gazetteer::lookup(query, gazetteer)
+                .with_context(|| format!("Could not resolve location \"{}\"", self.name))?;
+
+            self.latitude = self.latitude.or(Some(place.latitude));
+            self.longitude = self.longitude.or(Some(place.longitude));
+            self.altitude = self.altitude.or(place.elevation);
+            self.timezone = self.timezone.take().or(Some(place.timezone));
+        }
+
+        if let Some(name) = &self.timezone {
+            self.tz = Some(Tz::from_str(name)?);
+        }
+
+        Ok(())
+    }
+}
+
+fn home() -> String {
+    String::from("home")
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct Localization {
+    // the top level coordinates make up a location named "home".
     latitude: Option<f32>,
     longitude: Option<f32>,
     altitude: Option<f32>,
+    place: Option<String>,
+    timezone: Option<String>,
+
+    #[serde(default)]
+    locations: Vec<Location>,
+
+    // locations rendered by every location-aware module, defaults to the first
+    #[serde(default)]
+    show: Vec<String>,
+    #[serde(skip)]
+    selected: Vec<usize>,
+
+    gazetteer: Option<PathBuf>,
 
     #[serde(default)]
     allowed_services: Vec<Service>,
+
+    // decimals of precision each service is allowed to see.
+    #[serde(default)]
+    precision: HashMap<Service, Precision>,
+
+    // opt-in record of what was sent to whom.
+    #[serde(default)]
+    pub audit_log: bool,
+    pub audit_log_path: Option<PathBuf>,
+
+    #[serde(skip)]
+    audit: Mutex<Vec<AuditRecord>>,
 }
 
-impl Localization {
+// A location as seen by a module. All access to the coordinates goes through
+// here so that the service permissions and precision are enforced.
+pub struct LocationView<'a> {
+    localization: &'a Localization,
+    location: &'a Location,
+}
+
+impl LocationView<'_> {
+    pub fn name(&self) -> &str {
+        &self.location.name
+    }
+
+    // the timezone never leaves the machine, so it isn't gated by service.
+    pub fn timezone(&self) -> Option<Tz> {
+        self.location.tz
+    }
+
+    // exact coordinates, for math that stays on the machine like distances.
+    // Never put these in a request, use `get_coordinates` for that.
+    pub fn coordinates(&self) -> Option<(f32, f32)> {
+        self.location.latitude.zip(self.location.longitude)
+    }
+
+    // in meters. No service is sent the altitude, it's only used for things
+    // like density altitude, so it isn't rounded or gated.
+    pub fn altitude(&self) -> Option<f32> {
+        self.location.altitude
+    }
+
     // this function allows services to access the coordinates
     // it's not secure and is easy to "fool", but all of the modules are
     // isolated and trusted for now.
+    // coordinates are rounded here, so modules should pass them on as is.
     pub fn get_coordinates(&self, service: &Service) -> Option<(f32, f32)> {
-        if self.allowed_services.contains(service)
-            && self.latitude.is_some()
-            && self.longitude.is_some()
+        if !self.localization.allowed_services.contains(service) {
+            return None;
+        }
+
+        let precision = self.localization.precision(service);
+
+        match (self.location.latitude, self.location.longitude) {
+            (Some(lat), Some(long)) => {
+                let coords = (precision.apply(lat), precision.apply(long));
+                self.record_release(service, |r| r.coordinates = Some(coords));
+                Some(coords)
+            }
+            _ => None,
+        }
+    }
+
+    fn record_release<F: FnOnce(&mut AuditRecord)>(&self, service: &Service, f: F) {
+        let Ok(mut audit) = self.localization.audit.lock() else {
+            return;
+        };
+
+        let pending = audit
+            .iter_mut()
+            .find(|r| r.service == *service && r.location == self.location.name && r.url.is_none());
+
+        match pending {
+            Some(r) => f(r),
+            None => {
+                let mut r = AuditRecord {
+                    service: *service,
+                    location: self.location.name.clone(),
+                    coordinates: None,
+                    url: None,
+                };
+                f(&mut r);
+                audit.push(r);
+            }
+        }
+    }
+
+    // modules call this for every request that contains location data.
+    pub fn audit_request(&self, service: &Service, url: &str) {
+        let Ok(mut audit) = self.localization.audit.lock() else {
+            return;
+        };
+
+        let mut record = AuditRecord {
+            service: *service,
+            location: self.location.name.clone(),
+            coordinates: None,
+            url: Some(url.to_string()),
+        };
+
+        for r in audit
+            .iter()
+            .filter(|r| r.service == *service && r.location == self.location.name)
         {
-            Some((self.latitude.unwrap(), self.longitude.unwrap()))
-        } else {
-            None
+            record.coordinates = record.coordinates.or(r.coordinates);
         }
+
+        audit.push(record);
     }
-    #[allow(dead_code)]
-    pub fn get_altitude(&self, service: &Service) -> Option<f32> {
-        if self.allowed_services.contains(service) {
-            self.altitude
-        } else {
-            None
+}
+
+impl Localization {
+    fn resolve(&mut self, selected: Option<&str>) -> Result<()> {
+        let has_home = self.latitude.is_some() || self.longitude.is_some() || self.place.is_some();
+
+        if has_home {
+            let home = Location {
+                name: home(),
+                latitude: self.latitude,
+                longitude: self.longitude,
+                altitude: self.altitude,
+                place: self.place.clone(),
+                timezone: self.timezone.clone(),
+                tz: None,
+            };
+            self.locations.insert(0, home);
+        }
+
+        // names pick locations and key the audit log, so they have to be unique
+        for (i, location) in self.locations.iter().enumerate() {
+            if self.locations[..i].iter().any(|l| l.name == location.name) {
+                if has_home && location.name == home() {
+                    bail!(
+                        "A location in [localization] is named \"{}\", which is the name of the top level coordinates. Rename it.",
+                        location.name
+                    );
+                }
+                bail!(
+                    "More than one location in [localization] is named \"{}\".",
+                    location.name
+                );
+            }
+        }
+
+        for location in self.locations.iter_mut() {
+            location.resolve(self.gazetteer.as_deref())?;
         }
+
+        let index = |name: &str| {
+            self.locations
+                .iter()
+                .position(|l| l.name == name)
+                .with_context(|| format!("No location named \"{name}\" in [localization]"))
+        };
+
+        self.selected = match selected {
+            Some(name) => vec![index(name)?],
+            None if !self.show.is_empty() => self
+                .show
+                .iter()
+                .map(|name| index(name))
+                .collect::<Result<Vec<_>>>()?,
+            None if !self.locations.is_empty() => vec![0],
+            None => vec![],
+        };
+
+        Ok(())
+    }
+
+    // the locations every location-aware module should render.
+    pub fn selected(&self) -> Vec<LocationView<'_>> {
+        self.selected
+            .iter()
+            .map(|i| LocationView {
+                localization: self,
+                location: &self.locations[*i],
+            })
+            .collect()
+    }
+
+    // modules label their output by location when showing more than one.
+    pub fn is_multi(&self) -> bool {
+        self.selected.len() > 1
+    }
+
+    fn precision(&self, service: &Service) -> Precision {
+        self.precision
+            .get(service)
+            .copied()
+            .unwrap_or(service.default_precision())
+    }
+
+    // releases that were followed by a request are folded into that request.
+    pub fn audit_records(&self) -> Vec<AuditRecord> {
+        let Ok(audit) = self.audit.lock() else {
+            return vec![];
+        };
+
+        audit
+            .iter()
+            .filter(|r| {
+                r.url.is_some()
+                    || !audit.iter().any(|other| {
+                        other.service == r.service
+                            && other.location == r.location
+                            && other.url.is_some()
+                    })
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn policy(&self) -> Vec<(Service, bool, Precision)> {
+        Service::ALL
+            .iter()
+            .map(|s| (*s, self.allowed_services.contains(s), self.precision(s)))
+            .collect()
     }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Wxer {
     pub addresses: Vec<String>,
+    // try every address at once and use whichever answers first.
+    #[serde(default)]
+    pub race: bool,
+
+    #[serde(skip)]
+    pub health: Mutex<Option<WxerHealth>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -279,6 +642,53 @@ pub enum WxParams {
     #[serde(alias = "clouds", alias = "cloud_layers")]
     Cloud,
 
+    #[serde(alias = "precipitation", alias = "qpf")]
+    Precip,
+
+    #[serde(
+        alias = "precip_probability",
+        alias = "precipitation_probability",
+        alias = "precip_chance",
+        alias = "pop"
+    )]
+    PrecipProbability,
+
+    #[serde(alias = "snow")]
+    Snowfall,
+
+    #[serde(alias = "precip_today", alias = "daily_precip")]
+    PrecipToday,
+
+    #[serde(alias = "gusts", alias = "wind_gust", alias = "wind_gusts")]
+    Gust,
+
+    #[serde(alias = "cloud_cover", alias = "sky_cover", alias = "cover")]
+    CloudCover,
+
+    #[serde(alias = "uv", alias = "uv_index", alias = "uvi")]
+    UvIndex,
+
+    #[serde(alias = "wet_bulb", alias = "wetbulb_temp", alias = "tw")]
+    WetBulb,
+
+    #[serde(
+        alias = "WBGT",
+        alias = "wet_bulb_globe",
+        alias = "wet_bulb_globe_temp"
+    )]
+    Wbgt,
+
+    Humidex,
+
+    #[serde(alias = "frost_point", alias = "frost")]
+    FrostPoint,
+
+    #[serde(alias = "density_altitude", alias = "da")]
+    DensityAltitude,
+
+    #[serde(alias = "pressure_altitude", alias = "pa")]
+    PressureAltitude,
+
     #[serde(alias = "sbcape", alias = "CAPE")]
     Cape,
 
@@ -304,6 +714,11 @@ pub struct ForecastConfig {
     verbose: ForecastVerboseParams,
     extra_verbose: ForecastVerboseParams,
 
+    // TAFs shown after the forecast, by ICAO id or the name of a taf source
+    // in [weather.stations].
+    #[serde(default)]
+    pub tafs: Vec<String>,
+
     #[serde(skip)]
     pub selected: ForecastVerboseParams,
 }
@@ -312,15 +727,602 @@ pub struct ForecastConfig {
 pub struct ConditionsVerboseParams {
     pub parameters: Vec<WxParams>,
     pub sources: Vec<String>,
+    // parameters drawn as a sparkline under each station line.
+    #[serde(default)]
+    pub sparklines: Vec<WxParams>,
     // pub hours: Vec<u32>
 
     // #[serde(skip)]
     // pub stations: Vec<WxConditionStation>,
 }
 
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StationLayer {
+    Indoor,
+    #[serde(
+        alias = "near_surface",
+        alias = "surface",
+        alias = "outdoor",
+        alias = "2m"
+    )]
+    NearSurface,
+}
+
+// Windows are in minutes, thresholds in the units the parameter is shown in
+// (F, mb, kts, mi, %, J/kg, dam). A rapid change needs both the rapid and the
+// rapid_confirm change to be exceeded.
+#[derive(Debug, Clone, Copy)]
+pub struct TrendConfig {
+    pub enabled: bool,
+    pub change_window: i64,
+    pub change: f32,
+    pub rapid_window: i64,
+    pub rapid: f32,
+    pub rapid_confirm_window: i64,
+    pub rapid_confirm: f32,
+}
+
+impl TrendConfig {
+    const fn new(windows: (i64, i64, i64), change: f32, rapid: f32, rapid_confirm: f32) -> Self {
+        TrendConfig {
+            enabled: true,
+            change_window: windows.0,
+            change,
+            rapid_window: windows.1,
+            rapid,
+            rapid_confirm_window: windows.2,
+            rapid_confirm,
+        }
+    }
+
+    // used when a parameter has no trend configured.
+    pub fn default_for(param: &WxParams, indoor: bool) -> Option<TrendConfig> {
+        match param {
+            WxParams::Temperature if indoor => Some(TrendConfig::new((120, 60, 60), 2., 2., 2.)),
+            WxParams::Temperature | WxParams::Dewpoint => {
+                Some(TrendConfig::new((120, 15, 60), 4., 2., 4.))
+            }
+            WxParams::Pressure => Some(TrendConfig::new((360, 15, 180), 3., 1., 2.)),
+            WxParams::RelativeHumidity => Some(TrendConfig::new((120, 15, 60), 10., 5., 15.)),
+            WxParams::Wind | WxParams::Gust => Some(TrendConfig::new((60, 15, 60), 5., 5., 10.)),
+            WxParams::Visibility => Some(TrendConfig::new((60, 15, 60), 2., 1., 3.)),
+            WxParams::Cape => Some(TrendConfig::new((180, 60, 180), 500., 300., 1000.)),
+            WxParams::Height500mb => Some(TrendConfig::new((360, 60, 180), 3., 1., 3.)),
+            _ => None,
+        }
+    }
+}
+
+// a trend as written in the config, anything left out comes from the default.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+pub struct TrendSettings {
+    enabled: Option<bool>,
+    change_window: Option<i64>,
+    change: Option<f32>,
+    rapid_window: Option<i64>,
+    rapid: Option<f32>,
+    rapid_confirm_window: Option<i64>,
+    rapid_confirm: Option<f32>,
+}
+
+// a trend field, or a note that it's missing.
+fn field<T: Default>(v: Option<T>, name: &'static str, missing: &mut Vec<&str>) -> T {
+    v.unwrap_or_else(|| {
+        missing.push(name);
+        T::default()
+    })
+}
+
+impl TrendSettings {
+    // `over` wins wherever it sets something.
+    fn merge(&self, over: &TrendSettings) -> TrendSettings {
+        TrendSettings {
+            enabled: over.enabled.or(self.enabled),
+            change_window: over.change_window.or(self.change_window),
+            change: over.change.or(self.change),
+            rapid_window: over.rapid_window.or(self.rapid_window),
+            rapid: over.rapid.or(self.rapid),
+            rapid_confirm_window: over.rapid_confirm_window.or(self.rapid_confirm_window),
+            rapid_confirm: over.rapid_confirm.or(self.rapid_confirm),
+        }
+    }
+
+    // errors with whatever is still missing once the default fills in.
+    fn apply(&self, base: Option<TrendConfig>) -> Result<TrendConfig, String> {
+        let mut missing = vec![];
+
+        let trend = TrendConfig {
+            enabled: self.enabled.or(base.map(|b| b.enabled)).unwrap_or(true),
+            change_window: field(
+                self.change_window.or(base.map(|b| b.change_window)),
+                "change_window",
+                &mut missing,
+            ),
+            change: field(
+                self.change.or(base.map(|b| b.change)),
+                "change",
+                &mut missing,
+            ),
+            rapid_window: field(
+                self.rapid_window.or(base.map(|b| b.rapid_window)),
+                "rapid_window",
+                &mut missing,
+            ),
+            rapid: field(self.rapid.or(base.map(|b| b.rapid)), "rapid", &mut missing),
+            rapid_confirm_window: field(
+                self.rapid_confirm_window
+                    .or(base.map(|b| b.rapid_confirm_window)),
+                "rapid_confirm_window",
+                &mut missing,
+            ),
+            rapid_confirm: field(
+                self.rapid_confirm.or(base.map(|b| b.rapid_confirm)),
+                "rapid_confirm",
+                &mut missing,
+            ),
+        };
+
+        if missing.is_empty() {
+            Ok(trend)
+        } else {
+            Err(format!(
+                "there's no default trend to fill in {}",
+                missing.join(", ")
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Dimension {
+    Temperature,
+    Pressure,
+    Speed,
+    Length,
+    Fraction,
+    Energy,
+    Index,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ScaleUnit {
+    #[serde(alias = "F", alias = "fahrenheit")]
+    F,
+    #[serde(alias = "C", alias = "celsius")]
+    C,
+    #[serde(alias = "hpa", alias = "hPa", alias = "mbar")]
+    Mb,
+    #[serde(alias = "inHg")]
+    InHg,
+    #[serde(alias = "kt", alias = "knots")]
+    Kts,
+    Mph,
+    #[serde(alias = "km/h", alias = "kph")]
+    Kmh,
+    #[serde(alias = "m/s")]
+    Ms,
+    #[serde(alias = "miles")]
+    Mi,
+    Km,
+    #[serde(alias = "meters")]
+    M,
+    #[serde(alias = "feet")]
+    Ft,
+    Dam,
+    #[serde(alias = "inch", alias = "inches")]
+    In,
+    Mm,
+    #[serde(alias = "%")]
+    Percent,
+    #[serde(alias = "j/kg", alias = "J/kg")]
+    Jkg,
+    // unitless, like the UV index
+    Index,
+}
+
+impl ScaleUnit {
+    // base = value * scale + offset, in C, hPa, m/s, m, % or J/kg.
+    fn base(&self) -> (Dimension, f32, f32) {
+        match self {
+            ScaleUnit::F => (Dimension::Temperature, 5. / 9., -32. * 5. / 9.),
+            ScaleUnit::C => (Dimension::Temperature, 1., 0.),
+            ScaleUnit::Mb => (Dimension::Pressure, 1., 0.),
+            ScaleUnit::InHg => (Dimension::Pressure, 33.8639, 0.),
+            ScaleUnit::Kts => (Dimension::Speed, 0.514444, 0.),
+            ScaleUnit::Mph => (Dimension::Speed, 0.44704, 0.),
+            ScaleUnit::Kmh => (Dimension::Speed, 1. / 3.6, 0.),
+            ScaleUnit::Ms => (Dimension::Speed, 1., 0.),
+            ScaleUnit::Mi => (Dimension::Length, 1609.344, 0.),
+            ScaleUnit::Km => (Dimension::Length, 1000., 0.),
+            ScaleUnit::M => (Dimension::Length, 1., 0.),
+            ScaleUnit::Ft => (Dimension::Length, 0.3048, 0.),
+            ScaleUnit::Dam => (Dimension::Length, 10., 0.),
+            ScaleUnit::In => (Dimension::Length, 0.0254, 0.),
+            ScaleUnit::Mm => (Dimension::Length, 0.001, 0.),
+            ScaleUnit::Percent => (Dimension::Fraction, 1., 0.),
+            ScaleUnit::Jkg => (Dimension::Energy, 1., 0.),
+            ScaleUnit::Index => (Dimension::Index, 1., 0.),
+        }
+    }
+
+    pub fn convert(&self, value: f32, to: ScaleUnit) -> Option<f32> {
+        let (from_dim, from_scale, from_offset) = self.base();
+        let (to_dim, to_scale, to_offset) = to.base();
+
+        if from_dim != to_dim {
+            return None;
+        }
+
+        Some((value * from_scale + from_offset - to_offset) / to_scale)
+    }
+
+    // the unit each parameter is displayed in.
+    fn display_unit(param: &WxParams) -> Option<ScaleUnit> {
+        match param {
+            WxParams::Temperature | WxParams::ApparentTemp | WxParams::Dewpoint => {
+                Some(ScaleUnit::F)
+            }
+            WxParams::Pressure => Some(ScaleUnit::Mb),
+            WxParams::Wind | WxParams::Wind250mb => Some(ScaleUnit::Kts),
+            WxParams::Visibility => Some(ScaleUnit::Mi),
+            WxParams::RelativeHumidity => Some(ScaleUnit::Percent),
+            WxParams::Cape => Some(ScaleUnit::Jkg),
+            WxParams::Height500mb => Some(ScaleUnit::Dam),
+            WxParams::Precip | WxParams::Snowfall | WxParams::PrecipToday => Some(ScaleUnit::In),
+            WxParams::PrecipProbability | WxParams::CloudCover => Some(ScaleUnit::Percent),
+            WxParams::Gust => Some(ScaleUnit::Kts),
+            WxParams::UvIndex | WxParams::Humidex => Some(ScaleUnit::Index),
+            WxParams::WetBulb | WxParams::Wbgt | WxParams::FrostPoint => Some(ScaleUnit::F),
+            WxParams::DensityAltitude | WxParams::PressureAltitude => Some(ScaleUnit::Ft),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ColorStop {
+    // applies to values below this, or everything left if unset.
+    below: Option<f32>,
+    style: Vec<common::TermStyle>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ColorScale {
+    // unit the breakpoints are written in, defaults to the displayed unit.
+    unit: Option<ScaleUnit>,
+    stops: Vec<ColorStop>,
+
+    // breakpoints converted to the displayed unit
+    #[serde(skip)]
+    resolved: Vec<(Option<f32>, String)>,
+}
+
+impl ColorScale {
+    fn resolve(&mut self, param: &WxParams) -> Result<()> {
+        let display = ScaleUnit::display_unit(param)
+            .with_context(|| format!("{param:?} does not support color scales."))?;
+        let unit = self.unit.unwrap_or(display);
+
+        if unit.base().0 != display.base().0 {
+            bail!("{unit:?} is not a valid unit for {param:?} colors.");
+        }
+
+        self.resolved = self
+            .stops
+            .iter()
+            .map(|stop| {
+                (
+                    stop.below.and_then(|b| unit.convert(b, display)),
+                    common::Style::string(&stop.style),
+                )
+            })
+            .collect();
+
+        Ok(())
+    }
+
+    // value is in the displayed unit.
+    pub fn style(&self, value: f32) -> Option<String> {
+        self.resolved
+            .iter()
+            .find(|(below, _)| below.is_none_or(|b| value < b))
+            .map(|(_, style)| style.clone())
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WxerRange {
+    Latest,
+    #[default]
+    Hourly,
+    // also shows a "today so far" line
+    Daily,
+    // any other endpoint the wxer server provides
+    #[serde(untagged)]
+    Custom(String),
+}
+
+impl WxerRange {
+    pub fn name(&self) -> &str {
+        match self {
+            WxerRange::Latest => "latest",
+            WxerRange::Hourly => "hourly",
+            WxerRange::Daily => "daily",
+            WxerRange::Custom(s) => s,
+        }
+    }
+}
+
+// what a column or field from an observation source holds.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ObsField {
+    #[serde(alias = "temp")]
+    Temperature,
+    #[serde(alias = "dew")]
+    Dewpoint,
+    #[serde(alias = "rh", alias = "humidity")]
+    RelativeHumidity,
+    #[serde(alias = "station_pressure")]
+    Pressure,
+    #[serde(alias = "mslp", alias = "slp")]
+    SeaLevelPressure,
+    Altimeter,
+    #[serde(alias = "wind")]
+    WindSpeed,
+    #[serde(alias = "wind_dir")]
+    WindDirection,
+    #[serde(alias = "gust")]
+    WindGust,
+    #[serde(alias = "clouds")]
+    CloudCover,
+    #[serde(alias = "uv")]
+    UvIndex,
+    #[serde(alias = "vis")]
+    Visibility,
+    // since the previous observation
+    Precip,
+    PrecipToday,
+    #[serde(alias = "indoor_temp")]
+    IndoorTemperature,
+    #[serde(alias = "indoor_dew")]
+    IndoorDewpoint,
+    #[serde(alias = "indoor_rh", alias = "indoor_humidity")]
+    IndoorRelativeHumidity,
+}
+
+impl ObsField {
+    // the unit values are stored in, and assumed if none is given.
+    pub fn unit(&self) -> Option<ScaleUnit> {
+        match self {
+            ObsField::Temperature
+            | ObsField::Dewpoint
+            | ObsField::IndoorTemperature
+            | ObsField::IndoorDewpoint => Some(ScaleUnit::F),
+            ObsField::RelativeHumidity | ObsField::IndoorRelativeHumidity => {
+                Some(ScaleUnit::Percent)
+            }
+            ObsField::Pressure | ObsField::SeaLevelPressure | ObsField::Altimeter => {
+                Some(ScaleUnit::Mb)
+            }
+            ObsField::WindSpeed | ObsField::WindGust => Some(ScaleUnit::Kts),
+            ObsField::CloudCover => Some(ScaleUnit::Percent),
+            ObsField::Visibility => Some(ScaleUnit::Mi),
+            ObsField::Precip | ObsField::PrecipToday => Some(ScaleUnit::In),
+            // degrees, and the index has no unit
+            ObsField::WindDirection | ObsField::UvIndex => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FieldMapping {
+    pub field: ObsField,
+    pub unit: Option<ScaleUnit>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    #[default]
+    Wxer,
+    Csv,
+    Metar,
+    Json,
+    // only for the forecast's tafs list
+    Taf,
+}
+
+// metadata for a single source in current conditions, keyed by the source
+// name used in the `sources` lists.
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct StationConfig {
+    // indoor stations read the indoor layer and use the indoor color scale.
+    #[serde(default)]
+    pub indoor: bool,
+    pub name: Option<String>,
+    // layer to read temperature, dewpoint and humidity from.
+    pub layer: Option<StationLayer>,
+    // in meters, replaces the altitude reported by the source.
+    pub elevation: Option<f32>,
+    // only these parameters are shown for this source, if set.
+    pub parameters: Option<Vec<WxParams>>,
+    // overrides [weather.trends] for this source.
+    #[serde(default)]
+    pub trends: HashMap<WxParams, TrendSettings>,
+    // overrides [weather.colors] for this source.
+    #[serde(default)]
+    pub colors: HashMap<WxParams, ColorScale>,
+    // minutes, overrides [weather] max_age for this source.
+    max_age: Option<i64>,
+    // which wxer endpoint to request, and optionally only the last `window`
+    // hours of what it returns.
+    #[serde(default)]
+    pub range: WxerRange,
+    pub window: Option<i64>,
+
+    // sources other than wxer
+    #[serde(rename = "type", default)]
+    pub kind: SourceKind,
+    // either may contain strftime codes for the current date, like %Y or %j
+    pub url: Option<String>,
+    pub path: Option<String>,
+    pub latitude: Option<f32>,
+    pub longitude: Option<f32>,
+    // IANA name, for timestamps without an offset. Defaults to local time.
+    pub timezone: Option<String>,
+    // ICAO id for metar sources, defaults to the source name. Replaces
+    // {station} in the url or path.
+    pub station: Option<String>,
+
+    // csv sources
+    pub datetime_column: Option<String>,
+    // for json sources too, which also take unix timestamps
+    pub datetime_format: Option<String>,
+    // lines before the header, like a Campbell TOA5 file's metadata line
+    #[serde(default)]
+    pub skip_rows: usize,
+    // column name to what it holds
+    #[serde(default)]
+    pub columns: HashMap<String, FieldMapping>,
+
+    // json sources, both use JSON pointers like "/outdoor/temperature"
+    pub datetime_pointer: Option<String>,
+    #[serde(default)]
+    pub fields: HashMap<String, FieldMapping>,
+
+    // a panel for pilots under the station line, shown anyway if there are
+    // runways to work out wind components for.
+    #[serde(default)]
+    pub aviation: bool,
+    #[serde(default)]
+    pub runways: Vec<Runway>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Runway {
+    // "16/34", or "34" for only one end
+    pub name: String,
+    // degrees, of the first end. In the same reference as the wind, true
+    // north for METARs.
+    pub heading: u16,
+    pub airport: Option<String>,
+}
+
+impl StationConfig {
+    pub fn layer(&self) -> StationLayer {
+        match self.layer {
+            Some(l) => l,
+            None if self.indoor => StationLayer::Indoor,
+            None => StationLayer::NearSurface,
+        }
+    }
+
+    // the default, overridden by [weather.trends], then by the source's own.
+    pub fn trend(
+        &self,
+        param: &WxParams,
+        global: &HashMap<WxParams, TrendSettings>,
+    ) -> Option<TrendConfig> {
+        // incomplete trends are caught when the config is read
+        self.resolve_trend(param, global).ok().flatten()
+    }
+
+    fn resolve_trend(
+        &self,
+        param: &WxParams,
+        global: &HashMap<WxParams, TrendSettings>,
+    ) -> Result<Option<TrendConfig>, String> {
+        let default = TrendConfig::default_for(param, self.indoor);
+
+        let settings = match (global.get(param), self.trends.get(param)) {
+            (None, None) => return Ok(default.filter(|t| t.enabled)),
+            (Some(global), Some(own)) => global.merge(own),
+            (Some(settings), None) | (None, Some(settings)) => *settings,
+        };
+
+        // turning a trend off doesn't need the rest of it
+        if settings.enabled == Some(false) {
+            return Ok(None);
+        }
+
+        Ok(Some(settings.apply(default)?).filter(|t| t.enabled))
+    }
+
+    // indoor temperatures keep their own scale unless the source sets one.
+    pub fn color_scale<'a>(
+        &'a self,
+        param: &WxParams,
+        global: &'a HashMap<WxParams, ColorScale>,
+    ) -> Option<&'a ColorScale> {
+        match self.colors.get(param) {
+            Some(scale) => Some(scale),
+            None if self.indoor && *param == WxParams::Temperature => None,
+            None => global.get(param),
+        }
+    }
+
+    // how old the latest observation can be, and the longest gap trends are
+    // computed across.
+    pub fn max_age(&self, global: i64) -> chrono::Duration {
+        chrono::Duration::minutes(self.max_age.unwrap_or(global))
+    }
+
+    pub fn shows(&self, param: &WxParams) -> bool {
+        match &self.parameters {
+            Some(p) => p.contains(param),
+            None => true,
+        }
+    }
+}
+
+fn sparkline_hours() -> u32 {
+    24
+}
+
+fn max_age() -> i64 {
+    90
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConditionsLayout {
+    #[default]
+    Lines,
+    Table,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ConditionsConfig {
-    pub rename_stations: HashMap<String, String>,
+    #[serde(default)]
+    pub stations: HashMap<String, StationConfig>,
+    // replaced by `name` in [weather.stations], only read to say so.
+    rename_stations: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub trends: HashMap<WxParams, TrendSettings>,
+    #[serde(default = "sparkline_hours")]
+    pub sparkline_hours: u32,
+    #[serde(default)]
+    pub colors: HashMap<WxParams, ColorScale>,
+    // minutes before a source's latest observation is considered stale.
+    #[serde(default = "max_age")]
+    pub max_age: i64,
+    #[serde(default)]
+    pub hide_stale: bool,
+    // "Light rain showers" instead of "-SHRA", in forecasts too.
+    #[serde(default)]
+    pub describe_wx: bool,
+    #[serde(default)]
+    pub layout: ConditionsLayout,
+    // a row under the table showing how far apart the sources are.
+    #[serde(default)]
+    pub spread: bool,
+    // in the units shown, overriding the defaults in spread_limit.
+    #[serde(default)]
+    pub spread_limits: HashMap<WxParams, f32>,
 
     standard: ConditionsVerboseParams,
     verbose: ConditionsVerboseParams,
@@ -330,6 +1332,221 @@ pub struct ConditionsConfig {
     pub selected: ConditionsVerboseParams,
 }
 
+impl ConditionsConfig {
+    // every trend that's set has to end up complete, for listed sources without
+    // their own config too.
+    fn resolve_trends(&self) -> Result<()> {
+        let unconfigured = [&self.standard, &self.verbose, &self.extra_verbose]
+            .iter()
+            .flat_map(|p| &p.sources)
+            .any(|source| !self.stations.contains_key(source));
+
+        let default = StationConfig::default();
+        let stations = self
+            .stations
+            .iter()
+            .map(|(name, station)| (Some(name), station))
+            .chain(unconfigured.then_some((None, &default)));
+
+        for (name, station) in stations {
+            for param in self.trends.keys().chain(station.trends.keys()) {
+                if let Err(e) = station.resolve_trend(param, &self.trends) {
+                    match name.filter(|_| station.trends.contains_key(param)) {
+                        Some(name) => bail!("{param:?} trend for {name}: {e}."),
+                        None => bail!("{param:?} trend in [weather.trends]: {e}."),
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // how far sources can disagree before it's worth pointing out.
+    pub fn spread_limit(&self, param: &WxParams) -> Option<f32> {
+        if let Some(limit) = self.spread_limits.get(param) {
+            return Some(*limit);
+        }
+
+        match param {
+            WxParams::Temperature | WxParams::ApparentTemp | WxParams::Dewpoint => Some(5.),
+            WxParams::RelativeHumidity => Some(15.),
+            WxParams::Pressure => Some(3.),
+            WxParams::Wind => Some(10.),
+            WxParams::Gust => Some(15.),
+            WxParams::Visibility => Some(3.),
+            WxParams::Precip => Some(0.25),
+            WxParams::PrecipToday => Some(0.5),
+            WxParams::CloudCover => Some(40.),
+            WxParams::UvIndex => Some(3.),
+            _ => None,
+        }
+    }
+}
+
+// ALERTS ----------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Below,
+    AtMost,
+    Above,
+    AtLeast,
+}
+
+impl Comparison {
+    fn parse(s: &str) -> Option<Comparison> {
+        match s {
+            "<" => Some(Comparison::Below),
+            "<=" => Some(Comparison::AtMost),
+            ">" => Some(Comparison::Above),
+            ">=" => Some(Comparison::AtLeast),
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, value: f32, threshold: f32) -> bool {
+        match self {
+            Comparison::Below => value < threshold,
+            Comparison::AtMost => value <= threshold,
+            Comparison::Above => value > threshold,
+            Comparison::AtLeast => value >= threshold,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertScope {
+    // current conditions and the forecast
+    Everywhere,
+    // current conditions, from one source or any of them
+    Conditions(Option<String>),
+    // the forecast, optionally only this many hours ahead
+    Forecast(Option<i64>),
+}
+
+// "temperature < 32F at any source", "wind gust > 35kt in forecast next 24h"
+#[derive(Debug, Clone)]
+pub struct AlertRule {
+    pub text: String,
+    pub param: WxParams,
+    pub comparison: Comparison,
+    // in the unit the parameter is displayed in
+    pub threshold: f32,
+    pub scope: AlertScope,
+}
+
+fn from_name<'de, T: Deserialize<'de>>(s: &'de str) -> Option<T> {
+    use serde::de::IntoDeserializer;
+    T::deserialize(s.into_deserializer())
+        .map_err(|_: serde::de::value::Error| ())
+        .ok()
+}
+
+impl FromStr for AlertRule {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<AlertRule> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+
+        let op = words
+            .iter()
+            .position(|w| Comparison::parse(w).is_some())
+            .with_context(|| format!("\"{text}\" needs one of <, <=, > or >=."))?;
+        let comparison = Comparison::parse(words[op]).unwrap();
+
+        let name = words[..op].join("_").to_lowercase();
+        let param: WxParams =
+            from_name(&name).with_context(|| format!("\"{name}\" is not a parameter."))?;
+        let display = ScaleUnit::display_unit(&param)
+            .with_context(|| format!("{param:?} can not be used in alerts."))?;
+
+        let mut rest = words[op + 1..].iter().copied();
+        let value = rest
+            .next()
+            .with_context(|| format!("\"{text}\" is missing a value."))?;
+
+        // "32F", or "32 F"
+        let split = value
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+            .unwrap_or(value.len());
+        let number: f32 = value[..split]
+            .parse()
+            .with_context(|| format!("\"{value}\" is not a number."))?;
+
+        let mut rest = rest.peekable();
+        let unit = if split < value.len() {
+            Some(&value[split..])
+        } else {
+            // "in" starts "in forecast" more often than it means inches
+            rest.next_if(|w| *w != "in" && from_name::<ScaleUnit>(w).is_some())
+        };
+
+        let threshold = match unit {
+            Some(unit) => {
+                let unit: ScaleUnit =
+                    from_name(unit).with_context(|| format!("\"{unit}\" is not a unit."))?;
+                unit.convert(number, display)
+                    .with_context(|| format!("{unit:?} is not a valid unit for {param:?}."))?
+            }
+            None => number,
+        };
+
+        let rest: Vec<String> = rest.map(|w| w.to_lowercase()).collect();
+        let rest: Vec<&str> = rest.iter().map(|w| w.as_str()).collect();
+
+        let scope = match rest.as_slice() {
+            [] => AlertScope::Everywhere,
+            ["at", "any", "source"] | ["at", "any", "station"] => AlertScope::Conditions(None),
+            ["at", _] => AlertScope::Conditions(text.split_whitespace().last().map(String::from)),
+            ["in", "forecast"] | ["in", "the", "forecast"] => AlertScope::Forecast(None),
+            ["in", "forecast", "next", hours] | ["in", "the", "forecast", "next", hours] => {
+                let hours = hours.trim_end_matches(['h', 'r', 's']);
+                AlertScope::Forecast(Some(
+                    hours
+                        .parse()
+                        .with_context(|| format!("\"{hours}\" is not a number of hours."))?,
+                ))
+            }
+            _ => bail!(
+                "\"{text}\" should end with \"at any source\", \"at SOURCE\" or \"in forecast next 24h\"."
+            ),
+        };
+
+        Ok(AlertRule {
+            text: text.to_string(),
+            param,
+            comparison,
+            threshold,
+            scope,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct AlertsConfig {
+    #[serde(default)]
+    rules: Vec<String>,
+    // run with the alerts on stdin whenever any go off.
+    pub hook: Option<String>,
+
+    #[serde(skip)]
+    pub resolved: Vec<AlertRule>,
+    #[serde(skip)]
+    pub hits: Mutex<Vec<AlertHit>>,
+}
+
+impl AlertsConfig {
+    fn resolve(&mut self) -> Result<()> {
+        self.resolved = self
+            .rules
+            .iter()
+            .map(|r| r.parse())
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TeleconnectionsConfig {
     pub values: HashSet<Teleconnections>,
@@ -368,10 +1585,19 @@ pub struct Config {
     pub earthquakes: Earthquakes,
     pub forecast: ForecastConfig,
     pub datetime: DateTimeConfig,
+    #[serde(default)]
+    pub alerts: AlertsConfig,
 
     default_modules: DefaultModules,
     #[serde(skip)]
     pub enabled_modules: HashSet<Modules>,
+    #[serde(skip)]
+    pub verbosity: u8,
+
+    #[serde(skip)]
+    pub timings: Timings,
+    #[serde(skip)]
+    pub output: Output,
 }
 
 pub fn read_config_file(args: &Args) -> Result<Config> {
@@ -394,6 +1620,8 @@ pub fn read_config_file(args: &Args) -> Result<Config> {
         }
     }
 
+    config.verbosity = args.verbose;
+
     match args.verbose {
         0 => {
             config.forecast.selected = config.forecast.standard.clone();
@@ -454,5 +1682,29 @@ pub fn read_config_file(args: &Args) -> Result<Config> {
 
     config.datetime.timezones = tzs;
 
+    if let Some(renames) = &config.weather.rename_stations {
+        let (source, name) = renames
+            .iter()
+            .next()
+            .map_or(("APT", "Apartment"), |(k, v)| (k.as_str(), v.as_str()));
+        bail!(
+            "[weather] rename_stations is no longer read. Set the name on the source instead:\n\n[weather.stations.{source}]\nname = \"{name}\""
+        );
+    }
+
+    config.localization.resolve(args.location.as_deref())?;
+
+    for (param, scale) in config.weather.colors.iter_mut() {
+        scale.resolve(param)?;
+    }
+    for station in config.weather.stations.values_mut() {
+        for (param, scale) in station.colors.iter_mut() {
+            scale.resolve(param)?;
+        }
+    }
+
+    config.weather.resolve_trends()?;
+    config.alerts.resolve()?;
+
     Ok(config)
 }
@@ -129,11 +129,9 @@ pub fn datetime(config: &Config) {
     }
 
     match datetime_handler(config) {
-        Ok(s) => {
-            println!("{}", s)
-        }
-        Err(e) => {
-            println!("{}{}", common::title("DATETIME"), e)
-        }
+        Ok(s) => config.output.print(&s),
+        Err(e) => config
+            .output
+            .print(&format!("{}{}", common::title("DATETIME"), e)),
     }
 }
@@ -1,6 +1,7 @@
 use crate::common;
 use crate::config::Config;
 use crate::config::DistanceUnits;
+use crate::config::LocationView;
 use crate::config::Service;
 use common::Style;
 use common::TermStyle::*;
@@ -11,7 +12,7 @@ use std::collections::HashSet;
 use std::f32::consts::PI;
 use std::fmt;
 use std::hash::Hash;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -221,9 +222,11 @@ async fn get_earthquakes(
     url: &str,
     client: &reqwest::Client,
     home_coords: Option<(f32, f32)>,
+    config: &Config,
 ) -> Result<Vec<Earthquake>, String> {
     // dbg!(&url);
 
+    let start = Instant::now();
     let q = client
         .get(url)
         .timeout(Duration::from_secs(10))
@@ -233,9 +236,11 @@ async fn get_earthquakes(
     let r = q.map_err(|e| e.to_string())?;
 
     let t = r.text().await.map_err(|e| e.to_string())?;
+    config.timings.network(Modules::Earthquakes, start, t.len());
 
     // dbg!(&t);
 
+    let start = Instant::now();
     let usgs: USGSResponse = serde_json::from_str(&t).map_err(|e| e.to_string())?;
 
     let quakes = usgs
@@ -243,6 +248,7 @@ async fn get_earthquakes(
         .into_iter()
         .map(|x| Earthquake::from_USGS(x, home_coords))
         .collect::<Result<Vec<_>, String>>()?;
+    config.timings.parse(Modules::Earthquakes, start);
 
     Ok(quakes)
 }
@@ -285,16 +291,72 @@ fn convert_to_km(units: &DistanceUnits, value: f32) -> f32 {
     }
 }
 
+async fn local_earthquakes(
+    config: &Config,
+    client: &reqwest::Client,
+    location: &LocationView<'_>,
+) -> Result<String, String> {
+    let mut s = String::new();
+
+    let label = if config.localization.is_multi() {
+        format!("Local Earthquakes ({})", location.name())
+    } else {
+        String::from("Local Earthquakes")
+    };
+
+    // already rounded to the precision allowed for USGS.
+    let Some((lat, long)) = location.get_coordinates(&Service::Usgs) else {
+        s.push_str(&format!("{label} Disabled\n"));
+        return Ok(s);
+    };
+
+    let mut urls = vec![];
+
+    for rad in &config.earthquakes.local_search {
+        let mag = rad.min_magnitude;
+        let rad_km = convert_to_km(&config.earthquakes.units, rad.radius);
+        urls.push(format!("https://earthquake.usgs.gov/fdsnws/event/1/query?format=geojson&minmagnitude={mag}&latitude={lat}&longitude={long}&maxradiuskm={rad_km}&orderby=time"));
+    }
+
+    let mut futures = vec![];
+
+    for url in &urls {
+        location.audit_request(&Service::Usgs, url);
+        futures.push(get_earthquakes(url, client, location.coordinates(), config));
+    }
+
+    let local_quakes: Vec<Vec<Earthquake>> = try_join_all(futures).await?;
+    let start = Instant::now();
+    let local_quakes: HashSet<&Earthquake> = local_quakes.iter().flatten().collect();
+    let mut local_quakes: Vec<&&Earthquake> = local_quakes.iter().collect();
+    local_quakes.sort_by(|a, b| a.dist.partial_cmp(&b.dist).unwrap_or(Ordering::Greater));
+
+    if !local_quakes.is_empty() {
+        s.push_str(&format!("{label}:\n"));
+
+        for q in local_quakes {
+            s.push_str(&format!("{}", q));
+            // dbg!(&q);
+        }
+        s.push('\n');
+    } else {
+        s.push_str(&format!("{label}: None\n"));
+    }
+    config.timings.render(Modules::Earthquakes, start);
+
+    Ok(s)
+}
+
 async fn earthquake_handler(config: &Config) -> Result<String, String> {
     // "tallest skyscrapers" (>5 mag) for last 3 months of earthquakes
     // "local" earthquakes - earthquakes >2 mag within 150 km of PSM or >3 mag within 300km or >4 mag within 800km
 
     let mut s = common::title("EARTHQUAKES");
 
-    let coords_opt = config.localization.get_coordinates(&Service::Usgs);
+    let locations = config.localization.selected();
 
-    // this boolean can be adjusted later
-    let get_local_quakes: bool = coords_opt.is_some() && config.earthquakes.enable_local;
+    // distances to global quakes are measured from the first location
+    let coords_opt = locations.first().and_then(|l| l.coordinates());
 
     let now = Utc::now();
     let three_months_ago = now - chrono::Duration::days(180);
@@ -307,50 +369,22 @@ async fn earthquake_handler(config: &Config) -> Result<String, String> {
         "https://earthquake.usgs.gov/fdsnws/event/1/query?format=geojson&starttime={starttime}&minmagnitude=5&orderby=time"
     );
 
-    if get_local_quakes {
-        let lat_f = coords_opt.unwrap().0;
-        let long_f = coords_opt.unwrap().1;
-
-        let lat = lat_f.round() as i32;
-        let long = long_f.round() as i32;
-
-        let mut urls = vec![];
-
-        for rad in &config.earthquakes.local_search {
-            let mag = rad.min_magnitude;
-            let rad_km = convert_to_km(&config.earthquakes.units, rad.radius);
-            urls.push(format!("https://earthquake.usgs.gov/fdsnws/event/1/query?format=geojson&minmagnitude={mag}&latitude={lat}&longitude={long}&maxradiuskm={rad_km}&orderby=time"));
-        }
-
-        let mut futures = vec![];
+    if config.earthquakes.enable_local && !locations.is_empty() {
+        let futures = locations
+            .iter()
+            .map(|location| local_earthquakes(config, &client, location));
 
-        for url in &urls {
-            futures.push(get_earthquakes(url, &client, coords_opt));
-        }
-
-        let local_quakes: Vec<Vec<Earthquake>> = try_join_all(futures).await?;
-        let local_quakes: HashSet<&Earthquake> = local_quakes.iter().flatten().collect();
-        let mut local_quakes: Vec<&&Earthquake> = local_quakes.iter().collect();
-        local_quakes.sort_by(|a, b| a.dist.partial_cmp(&b.dist).unwrap_or(Ordering::Greater));
-
-        if !local_quakes.is_empty() {
-            s.push_str("Local Earthquakes:\n");
-
-            for q in local_quakes {
-                s.push_str(&format!("{}", q));
-                // dbg!(&q);
-            }
-            s.push('\n');
-        } else {
-            s.push_str("Local Earthquakes: None\n");
+        for local in try_join_all(futures).await? {
+            s.push_str(&local);
         }
     } else {
         s.push_str("Local Earthquakes Disabled\n");
     }
 
     // global quakes
-    let v1 = get_earthquakes(&url1, &client, coords_opt).await?;
+    let v1 = get_earthquakes(&url1, &client, coords_opt, config).await?;
 
+    let start = Instant::now();
     let global_quakes = tallest_skyscrapers(&v1);
 
     s.push_str("Global Earthquakes:\n");
@@ -358,6 +392,7 @@ async fn earthquake_handler(config: &Config) -> Result<String, String> {
     for q in global_quakes {
         s.push_str(&format!("{}", q));
     }
+    config.timings.render(Modules::Earthquakes, start);
 
     Ok(s)
 }
@@ -372,11 +407,9 @@ pub async fn earthquakes(config: &Config) {
     }
 
     match earthquake_handler(config).await {
-        Ok(s) => {
-            println!("{}", s)
-        }
-        Err(e) => {
-            println!("{}{}", common::title("EARTHQUAKE"), e)
-        }
+        Ok(s) => config.output.print(&s),
+        Err(e) => config
+            .output
+            .print(&format!("{}{}", common::title("EARTHQUAKE"), e)),
     }
 }
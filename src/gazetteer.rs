@@ -0,0 +1,110 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+
+// GAZETTEER -------------------------------------------------------------------
+
+// Resolves place names offline using a GeoNames style cities file, e.g.
+// cities15000.txt from https://download.geonames.org/export/dump/
+// A small sample is bundled so common places work without any setup.
+const BUNDLED_CITIES: &str = include_str!("../data/cities.txt");
+
+// column indices in the GeoNames "geoname" table
+const NAME: usize = 1;
+const ASCII_NAME: usize = 2;
+const ALTERNATE_NAMES: usize = 3;
+const LATITUDE: usize = 4;
+const LONGITUDE: usize = 5;
+const COUNTRY_CODE: usize = 8;
+const ADMIN1_CODE: usize = 10;
+const POPULATION: usize = 14;
+const ELEVATION: usize = 15;
+const DEM: usize = 16;
+const TIMEZONE: usize = 17;
+
+#[derive(Debug, Clone)]
+pub struct Place {
+    pub latitude: f32,
+    pub longitude: f32,
+    pub elevation: Option<f32>,
+    pub timezone: String,
+    population: u64,
+}
+
+// "Portsmouth, NH" -> name "portsmouth", qualifiers ["nh"]
+// qualifiers are matched against the admin1 (state) code and country code.
+fn split_query(query: &str) -> (String, Vec<String>) {
+    let mut parts = query.split(',').map(|p| p.trim().to_lowercase());
+    let name = parts.next().unwrap_or_default();
+    let qualifiers = parts.filter(|p| !p.is_empty()).collect();
+    (name, qualifiers)
+}
+
+fn parse_line(cols: &[&str]) -> Option<Place> {
+    let latitude = cols.get(LATITUDE)?.parse().ok()?;
+    let longitude = cols.get(LONGITUDE)?.parse().ok()?;
+
+    // the DEM is -9999 where there is no data
+    let elevation = cols
+        .get(ELEVATION)
+        .and_then(|e| e.parse::<f32>().ok())
+        .or(cols
+            .get(DEM)
+            .and_then(|e| e.parse::<f32>().ok())
+            .filter(|e| *e > -9999.));
+
+    Some(Place {
+        latitude,
+        longitude,
+        elevation,
+        timezone: cols.get(TIMEZONE)?.to_string(),
+        population: cols
+            .get(POPULATION)
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(0),
+    })
+}
+
+fn matches(cols: &[&str], name: &str, qualifiers: &[String]) -> bool {
+    let col = |i: usize| cols.get(i).copied().unwrap_or("");
+
+    let name_matches = col(NAME).to_lowercase() == name
+        || col(ASCII_NAME).to_lowercase() == name
+        || col(ALTERNATE_NAMES)
+            .split(',')
+            .any(|alt| alt.to_lowercase() == name);
+
+    let admin1 = col(ADMIN1_CODE).to_lowercase();
+    let country = col(COUNTRY_CODE).to_lowercase();
+
+    name_matches && qualifiers.iter().all(|q| *q == admin1 || *q == country)
+}
+
+fn search(text: &str, query: &str) -> Option<Place> {
+    let (name, qualifiers) = split_query(query);
+
+    text.lines()
+        .filter(|l| !l.starts_with('#'))
+        .map(|l| l.split('\t').collect::<Vec<_>>())
+        .filter(|cols| matches(cols, &name, &qualifiers))
+        .filter_map(|cols| parse_line(&cols))
+        // the biggest place is usually the one people mean
+        .max_by_key(|p| p.population)
+}
+
+pub fn lookup(query: &str, gazetteer: Option<&Path>) -> Result<Place> {
+    let place = match gazetteer {
+        Some(path) => {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("Could not read gazetteer {}", path.display()))?;
+            search(&text, query)
+        }
+        None => search(BUNDLED_CITIES, query),
+    };
+
+    match place {
+        Some(p) => Ok(p),
+        None => bail!("Could not find \"{query}\" in the gazetteer."),
+    }
+}
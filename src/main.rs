@@ -2,33 +2,57 @@ mod common;
 mod config;
 mod datetime;
 mod earthquake;
+mod gazetteer;
+mod output;
+mod privacy;
 mod random;
 mod solarlunar;
 mod tides;
+mod timings;
 mod updates;
 mod wx;
 
 use std::env;
+use std::path::PathBuf;
+use std::time::Instant;
 
 use chrono::{Local, Utc};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 // HEAD MATTER -----------------------------------------------------------------
 
-fn header() {
+fn header(config: &config::Config) {
     let utc_now = Utc::now().format("(%H:%MZ)");
     let local_now = Local::now().format("%a %Y-%b-%d @ %I:%M:%S%p");
     let r = rand::random::<u32>();
 
-    println!(
+    config.output.print(&format!(
         "{}unifetch v{} {local_now} {utc_now} - {r:08X}",
         common::terminal_line('-'),
         env!("CARGO_PKG_VERSION")
-    );
+    ));
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Review what location data unifetch has shared with third parties.
+    Privacy {
+        #[command(subcommand)]
+        action: PrivacyCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum PrivacyCommand {
+    /// Summarize the audit log of coordinates sent to each service.
+    Report,
 }
 
 #[derive(Parser, Debug)]
 pub struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Reimplements all default values, equivalent to -rsweq. If no other flags are selected this is enabled by default.
     #[arg(short, long)]
     default: bool,
@@ -73,13 +97,27 @@ pub struct Args {
     #[arg(short = 'u', long)]
     disable_update_notif: bool,
 
+    /// Only show this named location from the [localization] config.
+    #[arg(short = 'L', long, value_name = "NAME")]
+    location: Option<String>,
+
     /// Add up to 2 v's to add details. Currently only for wx data.
     #[arg(short = 'v', long, action = clap::ArgAction::Count)]
     verbose: u8,
+
+    /// Prints the network, parse and render time of each module after the output.
+    #[arg(long)]
+    timings: bool,
+
+    /// Appends the module timings to a CSV file, for tracking over time.
+    #[arg(long, value_name = "FILE")]
+    timings_csv: Option<PathBuf>,
 }
 
 #[tokio::main]
 async fn main() {
+    let start = Instant::now();
+
     // parse args
     let mut args = Args::parse();
 
@@ -112,27 +150,62 @@ async fn main() {
 
     let config = config_opt.unwrap();
 
+    if let Some(Command::Privacy {
+        action: PrivacyCommand::Report,
+    }) = &args.command
+    {
+        match privacy::report(&config) {
+            Ok(s) => println!("{s}"),
+            Err(e) => println!(
+                "{}PRIVACY REPORT ERROR{}\n{e:?}",
+                common::Style::error(),
+                common::TermStyle::Reset
+            ),
+        }
+        return;
+    }
+
     // actually start doing stuff
 
+    // the alerts banner goes above everything else, so nothing is printed
+    // until the weather modules have had a chance to set off an alert.
+    let alerts = wx::alerts::enabled(&config);
+    if alerts {
+        config.output.hold();
+    }
+
     if !args.disable_header {
-        header();
+        header(&config);
     }
 
     // sync functions
     if config.enabled_modules.contains(&config::Modules::Random) {
-        random::random_section();
+        let render_start = Instant::now();
+        random::random_section(&config);
+        config.timings.render(config::Modules::Random, render_start);
     }
 
     if config.enabled_modules.contains(&config::Modules::DateTime) {
+        let render_start = Instant::now();
         datetime::datetime(&config);
+        config
+            .timings
+            .render(config::Modules::DateTime, render_start);
     }
 
     // async functions
     tokio::join!(
         updates::updates(&config),
         solarlunar::solar_lunar(&config),
-        wx::weather::current_conditions(&config),
-        wx::forecast::forecast(&config),
+        async {
+            tokio::join!(
+                wx::weather::current_conditions(&config),
+                wx::forecast::forecast(&config)
+            );
+            if alerts {
+                wx::alerts::alerts(&config).await;
+            }
+        },
         wx::tele::teleconnections(&config),
         // time_and_date();
 
@@ -154,4 +227,27 @@ async fn main() {
         tides::tides(&config),
         earthquake::earthquakes(&config)
     );
+
+    if let Err(e) = privacy::write_audit_log(&config) {
+        println!(
+            "{}Could not write privacy audit log{}\n{e:?}",
+            common::Style::error(),
+            common::TermStyle::Reset
+        );
+    }
+
+    if args.timings {
+        println!("{}", config.timings.report(start.elapsed()));
+    }
+
+    if let Some(path) = &args.timings_csv
+        && let Err(e) = config.timings.append_csv(path)
+    {
+        println!(
+            "{}Could not write timings to {}{}\n{e:?}",
+            common::Style::error(),
+            path.display(),
+            common::TermStyle::Reset
+        );
+    }
 }
@@ -0,0 +1,40 @@
+use std::sync::Mutex;
+
+// OUTPUT ----------------------------------------------------------------------
+
+// Modules print as soon as they finish. While held, their output is kept back
+// instead, so that something learned later can still be printed above it.
+#[derive(Debug, Default)]
+pub struct Output {
+    held: Mutex<Option<String>>,
+}
+
+impl Output {
+    pub fn hold(&self) {
+        if let Ok(mut held) = self.held.lock() {
+            held.get_or_insert_with(String::new);
+        }
+    }
+
+    pub fn print(&self, s: &str) {
+        if let Ok(mut held) = self.held.lock()
+            && let Some(held) = held.as_mut()
+        {
+            held.push_str(s);
+            held.push('\n');
+            return;
+        }
+
+        println!("{s}");
+    }
+
+    // prints `first`, then everything that was held back.
+    pub fn release(&self, first: &str) {
+        let held = self.held.lock().ok().and_then(|mut h| h.take());
+
+        print!("{first}");
+        if let Some(held) = held {
+            print!("{held}");
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::common;
+use crate::config::{Config, Service};
+use common::TermStyle::*;
+
+// PRIVACY AUDIT ---------------------------------------------------------------
+
+// What a single service was given during a run. `url` is None when a module
+// read the coordinates but never reported a request using them.
+#[derive(Debug, Clone)]
+pub struct AuditRecord {
+    pub service: Service,
+    pub location: String,
+    pub coordinates: Option<(f32, f32)>,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AuditEntry {
+    run: DateTime<Utc>,
+    service: Service,
+    #[serde(default)]
+    location: String,
+    coordinates: Option<(f32, f32)>,
+    url: Option<String>,
+}
+
+fn audit_log_path(config: &Config) -> Result<PathBuf> {
+    match &config.localization.audit_log_path {
+        Some(p) => Ok(p.clone()),
+        None => Ok(common::data_dir()?.join("privacy_audit.jsonl")),
+    }
+}
+
+pub fn write_audit_log(config: &Config) -> Result<()> {
+    if !config.localization.audit_log {
+        return Ok(());
+    }
+
+    let records = config.localization.audit_records();
+
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    let path = audit_log_path(config)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+    let run = Utc::now();
+
+    for r in records {
+        let entry = AuditEntry {
+            run,
+            service: r.service,
+            location: r.location,
+            coordinates: r.coordinates,
+            url: r.url,
+        };
+
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct ServiceSummary {
+    runs: BTreeSet<DateTime<Utc>>,
+    requests: usize,
+    unreported: usize,
+    coordinates: BTreeMap<String, usize>,
+    hosts: BTreeSet<String>,
+}
+
+fn host(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+        .unwrap_or(url.to_string())
+}
+
+fn counts_str(map: &BTreeMap<String, usize>) -> String {
+    if map.is_empty() {
+        return String::from("never");
+    }
+
+    map.iter()
+        .map(|(value, count)| format!("{value} ({count}x)"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn report(config: &Config) -> Result<String> {
+    let mut s = common::title("PRIVACY REPORT");
+
+    let path = audit_log_path(config)?;
+
+    if !config.localization.audit_log {
+        s.push_str(&format!(
+            "{}Auditing is disabled.{Reset} Set `audit_log = true` under [localization] to record future runs.\n",
+            common::Style::warning()
+        ));
+    }
+
+    s.push_str("Current policy:\n");
+    for (service, allowed, precision) in config.localization.policy() {
+        let status = if allowed { "allowed" } else { "denied" };
+        s.push_str(&format!(
+            "  {Bold}{}{Reset}: {status}, precision {precision}\n",
+            service.name()
+        ));
+    }
+    s.push('\n');
+
+    if !path.exists() {
+        s.push_str(&format!("No audit log found at {}\n", path.display()));
+        return Ok(s);
+    }
+
+    let text = fs::read_to_string(&path)
+        .with_context(|| format!("Could not read audit log at {}", path.display()))?;
+
+    let mut all_runs = BTreeSet::new();
+    let mut summaries: BTreeMap<&'static str, ServiceSummary> = BTreeMap::new();
+
+    for (idx, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let entry: AuditEntry = serde_json::from_str(line)
+            .with_context(|| format!("Malformed audit log entry on line {}", idx + 1))?;
+
+        all_runs.insert(entry.run);
+
+        let summary = summaries.entry(entry.service.name()).or_default();
+        summary.runs.insert(entry.run);
+
+        if let Some((lat, long)) = entry.coordinates {
+            *summary
+                .coordinates
+                .entry(format!(
+                    "{} {}",
+                    entry.location,
+                    common::coords_str((lat, long))
+                ))
+                .or_default() += 1;
+        }
+
+        match entry.url {
+            Some(url) => {
+                summary.requests += 1;
+                summary.hosts.insert(host(&url));
+            }
+            None => summary.unreported += 1,
+        }
+    }
+
+    match (all_runs.first(), all_runs.last()) {
+        (Some(first), Some(last)) => s.push_str(&format!(
+            "Audit log: {} ({} runs, {} to {})\n",
+            path.display(),
+            all_runs.len(),
+            first.format("%Y-%m-%d %H:%MZ"),
+            last.format("%Y-%m-%d %H:%MZ")
+        )),
+        _ => {
+            s.push_str(&format!("Audit log at {} is empty\n", path.display()));
+            return Ok(s);
+        }
+    }
+
+    for (name, summary) in summaries {
+        s.push_str(&format!(
+            "\n{Bold}{name}{Reset}: {} requests over {} runs\n",
+            summary.requests,
+            summary.runs.len()
+        ));
+
+        if summary.unreported > 0 {
+            s.push_str(&format!(
+                "  {}{} releases with no recorded URL{Reset}\n",
+                common::Style::warning(),
+                summary.unreported
+            ));
+        }
+
+        s.push_str(&format!(
+            "  Coordinates sent: {}\n",
+            counts_str(&summary.coordinates)
+        ));
+
+        if !summary.hosts.is_empty() {
+            s.push_str(&format!(
+                "  Hosts: {}\n",
+                summary.hosts.into_iter().collect::<Vec<_>>().join(", ")
+            ));
+        }
+    }
+
+    Ok(s)
+}
@@ -1,4 +1,5 @@
 use crate::common;
+use crate::config::Config;
 use common::Style;
 use common::TermStyle::*;
 
@@ -41,7 +42,7 @@ fn rand_date(rng: &mut ThreadRng) -> NaiveDate {
     opt_date.unwrap()
 }
 
-pub fn random_section() {
+pub fn random_section(config: &Config) {
     let mut s = common::title("RANDOM");
 
     let mut rng = rand::rng();
@@ -120,5 +121,5 @@ pub fn random_section() {
         date.format("%a")
     ));
 
-    println!("{}", s);
+    config.output.print(&s);
 }
@@ -3,9 +3,10 @@ use crate::config::{Config, Service};
 use common::TermStyle::*;
 
 use std::collections::HashMap;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use chrono::{Local, NaiveTime};
+use chrono::{Local, NaiveDate, NaiveTime, Offset, Utc};
+use futures::future::join_all;
 use serde_json::Value;
 
 fn parse_navy_times(v: &Value) -> Result<NaiveTime, String> {
@@ -37,7 +38,7 @@ fn string_from_rise_set_times(
     }
 }
 
-fn generate_solar_lunar_string(json: serde_json::Value) -> Result<String, String> {
+fn generate_solar_lunar_string(json: serde_json::Value, date: NaiveDate) -> Result<String, String> {
     // this entire function could be written better tbh
     let data = &json["properties"]["data"];
     let sundata = &data["sundata"];
@@ -109,7 +110,7 @@ fn generate_solar_lunar_string(json: serde_json::Value) -> Result<String, String
 
     Ok(format!(
         "For {Bold}{}{Reset}\n{}{}{}{}",
-        Local::now().format("%b %d"),
+        date.format("%b %d"),
         string_from_rise_set_times("Sun", "Rise", "Set", sunrise, sunset),
         string_from_rise_set_times("Twilight", "Begin", "End", twilight_start, twilight_end),
         string_from_rise_set_times("Moon", "Rise", "Set", moonrise, moonset),
@@ -117,56 +118,108 @@ fn generate_solar_lunar_string(json: serde_json::Value) -> Result<String, String
     ))
 } // dbg!(&r);
 
-use crate::config::Modules;
+use crate::config::{LocationView, Modules};
 
-pub async fn solar_lunar(config: &Config) {
-    if !config.enabled_modules.contains(&Modules::SolarLunar) {
-        return;
-    }
+async fn solar_lunar_location(config: &Config, location: &LocationView<'_>) -> String {
+    let mut s = String::new();
 
-    let mut s: String = common::title("SOLAR & LUNAR");
-
-    let coordinates_opt = config.localization.get_coordinates(&Service::Usno);
-
-    if coordinates_opt.is_none() {
-        println!(
-            "{s}Coordinates not provided, cannot get solar/lunar times from unknown location\n"
-        );
-        return;
+    if config.localization.is_multi() {
+        s.push_str(&common::location_heading(location.name()));
     }
 
-    let coords_str = common::coords_str(coordinates_opt.unwrap());
+    let Some(coordinates) = location.get_coordinates(&Service::Usno) else {
+        s.push_str("Coordinates not provided, cannot get solar/lunar times from unknown location");
+        return s;
+    };
+
+    let coords_str = common::coords_str(coordinates);
 
-    let now = Local::now();
+    let utc_now = Utc::now();
 
-    let tz_offset = now.offset().local_minus_utc() / 60 / 60;
+    // use the location's timezone if we know it, otherwise assume the machine
+    // is at the location.
+    let (date, tz_offset) = match location.timezone() {
+        Some(tz) => {
+            let now = utc_now.with_timezone(&tz);
+            (
+                now.date_naive(),
+                now.offset().fix().local_minus_utc() / 60 / 60,
+            )
+        }
+        None => {
+            let now = utc_now.with_timezone(&Local);
+            (now.date_naive(), now.offset().local_minus_utc() / 60 / 60)
+        }
+    };
 
     let mut map = HashMap::new();
 
-    map.insert("date", now.format("%Y-%m-%d").to_string());
+    map.insert("date", date.format("%Y-%m-%d").to_string());
     map.insert("coords", coords_str);
     map.insert("tz", tz_offset.to_string());
 
+    let url =
+        match reqwest::Url::parse_with_params("https://aa.usno.navy.mil/api/rstt/oneday", &map) {
+            Ok(url) => url,
+            Err(e) => {
+                s.push_str(&e.to_string());
+                return s;
+            }
+        };
+
+    location.audit_request(&Service::Usno, url.as_str());
+
     let client = reqwest::Client::new();
 
-    let r = client
-        .get("https://aa.usno.navy.mil/api/rstt/oneday")
-        .query(&map)
-        .timeout(Duration::from_secs(5));
+    let r = client.get(url).timeout(Duration::from_secs(5));
 
     // dbg!(&r);
 
-    let form = r.send().await;
+    let start = Instant::now();
+    let form = common::request_text(r.send().await).await;
+    let bytes = form.as_ref().map(|t| t.len()).unwrap_or(0);
+    config.timings.network(Modules::SolarLunar, start, bytes);
 
     // dbg!(&form);
 
-    match common::parse_request_loose_json(form).await {
-        Ok(json) => match generate_solar_lunar_string(json) {
+    let start = Instant::now();
+    let json = form.and_then(|t| common::parse_loose_json(&t));
+    config.timings.parse(Modules::SolarLunar, start);
+
+    let start = Instant::now();
+    match json {
+        Ok(json) => match generate_solar_lunar_string(json, date) {
             Ok(res) => s.push_str(&res),
             Err(res) => s.push_str(&res),
         },
         Err(e) => s.push_str(&e),
     }
+    config.timings.render(Modules::SolarLunar, start);
+
+    s
+}
+
+pub async fn solar_lunar(config: &Config) {
+    if !config.enabled_modules.contains(&Modules::SolarLunar) {
+        return;
+    }
+
+    let mut s: String = common::title("SOLAR & LUNAR");
+
+    let locations = config.localization.selected();
+
+    if locations.is_empty() {
+        config.output.print(&format!(
+            "{s}Coordinates not provided, cannot get solar/lunar times from unknown location\n"
+        ));
+        return;
+    }
+
+    let futures = locations
+        .iter()
+        .map(|location| solar_lunar_location(config, location));
+
+    s.push_str(&join_all(futures).await.join("\n\n"));
 
-    println!("{}\n", s);
+    config.output.print(&format!("{}\n", s));
 }
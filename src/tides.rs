@@ -1,4 +1,5 @@
 use std::fmt::Display;
+use std::time::Instant;
 
 use crate::common;
 use crate::config::Config;
@@ -14,6 +15,8 @@ pub struct TidalStation {
     id: u64,
     #[serde(alias = "name")]
     short_name: String,
+    // only shown when this named location is selected, or always if unset.
+    location: Option<String>,
 }
 
 use crate::config::Modules;
@@ -24,12 +27,10 @@ pub async fn tides(config: &Config) {
     }
 
     match tides_handler(config).await {
-        Ok(s) => {
-            println!("{}", s)
-        }
-        Err(e) => {
-            println!("{}{}", common::title("TIDES"), e)
-        }
+        Ok(s) => config.output.print(&s),
+        Err(e) => config
+            .output
+            .print(&format!("{}{}", common::title("TIDES"), e)),
     }
 }
 
@@ -87,7 +88,7 @@ fn high_low_style(peak: char) -> String {
     }
 }
 
-async fn do_tide_station(station: &TidalStation) -> Result<String, String> {
+async fn do_tide_station(station: &TidalStation, config: &Config) -> Result<String, String> {
     let station_id = station.id;
     let now = Utc::now();
     let yesterday = (now - Duration::days(1)).format("%Y%m%d");
@@ -99,9 +100,16 @@ async fn do_tide_station(station: &TidalStation) -> Result<String, String> {
 
     // dbg!(&url);
 
+    let start = Instant::now();
     let req = reqwest::get(url).await.map_err(|x| x.to_string())?;
     let text = req.text().await.map_err(|x| x.to_string())?;
+    config.timings.network(Modules::Tides, start, text.len());
+
+    let start = Instant::now();
     let tides: Tides = serde_json::from_str(&text).map_err(|x| x.to_string())?;
+    config.timings.parse(Modules::Tides, start);
+
+    let start = Instant::now();
 
     // find the first tide after now
     let mut idxs = vec![0, 1, 2]; // default to the first few
@@ -129,19 +137,44 @@ async fn do_tide_station(station: &TidalStation) -> Result<String, String> {
         .collect::<Vec<String>>()
         .join(", ");
 
+    config.timings.render(Modules::Tides, start);
+
     Ok(format!("{Bold}{}{Reset}: {s}\n", station.short_name))
 }
 
-async fn tides_handler(config: &Config) -> Result<String, String> {
-    let s = common::title("TIDES");
-
+async fn tide_stations(config: &Config, stations: Vec<&TidalStation>) -> Result<String, String> {
     let mut futures = vec![];
 
-    for station in &config.tides {
-        futures.push(do_tide_station(station))
+    for station in stations {
+        futures.push(do_tide_station(station, config))
     }
 
-    let string = try_join_all(futures).await?.join("");
+    Ok(try_join_all(futures).await?.join(""))
+}
+
+async fn tides_handler(config: &Config) -> Result<String, String> {
+    let mut s = common::title("TIDES");
+
+    let unassigned = config.tides.iter().filter(|t| t.location.is_none());
+    s.push_str(&tide_stations(config, unassigned.collect()).await?);
+
+    for location in config.localization.selected() {
+        let stations: Vec<&TidalStation> = config
+            .tides
+            .iter()
+            .filter(|t| t.location.as_deref() == Some(location.name()))
+            .collect();
+
+        if stations.is_empty() {
+            continue;
+        }
+
+        if config.localization.is_multi() {
+            s.push_str(&location_heading(location.name()));
+        }
+
+        s.push_str(&tide_stations(config, stations).await?);
+    }
 
-    Ok(s + &string)
+    Ok(s)
 }
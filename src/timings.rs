@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use chrono::Utc;
+
+use crate::common;
+use crate::config::Modules;
+
+// TIMINGS ---------------------------------------------------------------------
+
+#[derive(Debug, Default, Clone)]
+pub struct ModuleTimings {
+    network: Duration,
+    parse: Duration,
+    render: Duration,
+    bytes: usize,
+}
+
+// Nothing is read from a cache in place of a fetch, so there's no column for
+// cache hits.
+//
+// Every module runs concurrently and shares the config, so this is behind a
+// mutex. Network time is summed across all of a module's requests, even if
+// they were made in parallel.
+#[derive(Debug, Default)]
+pub struct Timings {
+    modules: Mutex<HashMap<Modules, ModuleTimings>>,
+}
+
+fn ms(d: Duration) -> f32 {
+    d.as_secs_f32() * 1000.
+}
+
+fn format_bytes(bytes: usize) -> String {
+    if bytes >= 1_000_000 {
+        format!("{:.1}MB", bytes as f32 / 1_000_000.)
+    } else if bytes >= 1_000 {
+        format!("{:.1}kB", bytes as f32 / 1_000.)
+    } else {
+        format!("{bytes}B")
+    }
+}
+
+impl Timings {
+    fn update<F: FnOnce(&mut ModuleTimings)>(&self, module: Modules, f: F) {
+        if let Ok(mut modules) = self.modules.lock() {
+            f(modules.entry(module).or_default());
+        }
+    }
+
+    pub fn network(&self, module: Modules, start: Instant, bytes: usize) {
+        let elapsed = start.elapsed();
+        self.update(module, |t| {
+            t.network += elapsed;
+            t.bytes += bytes;
+        });
+    }
+
+    pub fn parse(&self, module: Modules, start: Instant) {
+        let elapsed = start.elapsed();
+        self.update(module, |t| t.parse += elapsed);
+    }
+
+    pub fn render(&self, module: Modules, start: Instant) {
+        let elapsed = start.elapsed();
+        self.update(module, |t| t.render += elapsed);
+    }
+
+    fn sorted(&self) -> Vec<(Modules, ModuleTimings)> {
+        let mut v: Vec<(Modules, ModuleTimings)> = match self.modules.lock() {
+            Ok(m) => m.iter().map(|(k, v)| (*k, v.clone())).collect(),
+            Err(_) => vec![],
+        };
+        v.sort_by_key(|(m, _)| m.name());
+        v
+    }
+
+    pub fn report(&self, total: Duration) -> String {
+        let mut s = common::title("TIMINGS");
+
+        s.push_str(&format!(
+            "{:<18}{:>10}{:>10}{:>10}{:>12}\n",
+            "Module", "Network", "Parse", "Render", "Fetched"
+        ));
+
+        for (module, t) in self.sorted() {
+            s.push_str(&format!(
+                "{:<18}{:>8.0}ms{:>8.1}ms{:>8.1}ms{:>12}\n",
+                module.name(),
+                ms(t.network),
+                ms(t.parse),
+                ms(t.render),
+                format_bytes(t.bytes)
+            ));
+        }
+
+        s.push_str(&format!("Total wall time: {:.0}ms\n", ms(total)));
+
+        s
+    }
+
+    pub fn append_csv(&self, path: &Path) -> Result<()> {
+        let new_file = !path.exists();
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let mut writer = csv::Writer::from_writer(file);
+
+        if new_file {
+            writer.write_record([
+                "time",
+                "module",
+                "network_ms",
+                "parse_ms",
+                "render_ms",
+                "bytes",
+            ])?;
+        }
+
+        let now = Utc::now().to_rfc3339();
+
+        for (module, t) in self.sorted() {
+            writer.write_record([
+                now.clone(),
+                module.name().to_string(),
+                format!("{:.1}", ms(t.network)),
+                format!("{:.1}", ms(t.parse)),
+                format!("{:.1}", ms(t.render)),
+                t.bytes.to_string(),
+            ])?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use reqwest::Client;
@@ -31,16 +31,20 @@ fn version_greater(current: &str, origin: &str) -> Result<bool> {
     }
 }
 
-async fn latest_version() -> Result<String> {
+async fn latest_version(config: &Config) -> Result<String> {
     let client = Client::builder().timeout(Duration::from_secs(3)).build()?;
 
     let url = "https://raw.githubusercontent.com/Nicbudd/unifetch/master/Cargo.toml";
 
+    let start = Instant::now();
     let req = client.get(url).build()?;
     let resp = client.execute(req).await?;
     let body = resp.text().await?;
+    config.timings.network(Modules::Updates, start, body.len());
 
+    let start = Instant::now();
     let toml = body.parse::<Table>()?;
+    config.timings.parse(Modules::Updates, start);
 
     let version = toml
         .get("package")
@@ -60,18 +64,18 @@ pub async fn updates(config: &Config) {
         return;
     }
 
-    let latest: Result<String> = latest_version().await;
+    let latest: Result<String> = latest_version(config).await;
 
     if let Ok(latest) = latest {
         let this_version = env!("CARGO_PKG_VERSION");
 
         if version_greater(this_version, &latest).unwrap_or(false) {
-            println!(
+            config.output.print(&format!(
                 "{}Unifetch {Bold}{Green}{}{Reset} is available, you are running version {Red}{}{Reset}.\n  To get the latest version do: `git pull master && cargo build --release`",
                 common::title("VERSION"),
                 latest,
                 this_version
-            )
+            ))
         }
     }
 }
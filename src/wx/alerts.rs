@@ -0,0 +1,182 @@
+use crate::common;
+use crate::config::{AlertScope, Config, Modules};
+use crate::wx::{Entry, history_label, param_value};
+use common::TermStyle::*;
+
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+use chrono::{DateTime, Local, Utc};
+use chrono_tz::Tz;
+use wxer_lib::Layer;
+
+// ALERTS ----------------------------------------------------------------------
+
+// a rule that went off, and where.
+#[derive(Debug, Clone)]
+pub struct AlertHit {
+    rule: usize,
+    // "KPSM", or "the forecast for home, Mon 19 3PM"
+    place: String,
+    value: f32,
+}
+
+pub fn enabled(config: &Config) -> bool {
+    !config.alerts.resolved.is_empty()
+        && (config.enabled_modules.contains(&Modules::CurrentConditions)
+            || config.enabled_modules.contains(&Modules::Forecast))
+}
+
+fn record(config: &Config, hit: AlertHit) {
+    if let Ok(mut hits) = config.alerts.hits.lock() {
+        hits.push(hit);
+    }
+}
+
+// the latest observation from one source.
+pub fn check_conditions(config: &Config, source: &str, name: &str, e: &Entry, layer: Layer) {
+    for (i, rule) in config.alerts.resolved.iter().enumerate() {
+        match &rule.scope {
+            AlertScope::Everywhere | AlertScope::Conditions(None) => {}
+            AlertScope::Conditions(Some(s)) if s.eq_ignore_ascii_case(source) || s == name => {}
+            _ => continue,
+        }
+
+        if let Some(value) = param_value(e, &rule.param, layer)
+            && rule.comparison.matches(value, rule.threshold)
+        {
+            record(
+                config,
+                AlertHit {
+                    rule: i,
+                    place: name.to_string(),
+                    value,
+                },
+            );
+        }
+    }
+}
+
+// every forecast hour, not just the ones shown. Only the first hour a rule goes
+// off in is reported.
+pub fn check_forecast(config: &Config, location: Option<&str>, entries: &[Entry], tz: Option<Tz>) {
+    let now = Utc::now();
+
+    for (i, rule) in config.alerts.resolved.iter().enumerate() {
+        let until = match &rule.scope {
+            AlertScope::Everywhere | AlertScope::Forecast(None) => DateTime::<Utc>::MAX_UTC,
+            AlertScope::Forecast(Some(hours)) => now + chrono::Duration::hours(*hours),
+            AlertScope::Conditions(_) => continue,
+        };
+
+        let hit = entries
+            .iter()
+            .filter(|e| e.date_time + chrono::Duration::hours(1) > now && e.date_time <= until)
+            .find_map(|e| {
+                param_value(e, &rule.param, Layer::NearSurface)
+                    .filter(|v| rule.comparison.matches(*v, rule.threshold))
+                    .map(|v| (e.date_time, v))
+            });
+
+        if let Some((time, value)) = hit {
+            let time = match tz {
+                Some(tz) => time.with_timezone(&tz).format("%a %d %l%p").to_string(),
+                None => time.with_timezone(&Local).format("%a %d %l%p").to_string(),
+            };
+            let place = match location {
+                Some(location) => format!("the forecast for {location}, {time}"),
+                None => format!("the forecast, {time}"),
+            };
+
+            record(
+                config,
+                AlertHit {
+                    rule: i,
+                    place,
+                    value,
+                },
+            );
+        }
+    }
+}
+
+// hands the alerts to the hook on stdin.
+fn run_hook(hook: &str, text: &str) -> Result<(), String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(hook)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    // dropped before waiting, so the hook sees the end of its input. A hook
+    // that doesn't read it is fine.
+    if let Some(mut stdin) = child.stdin.take()
+        && let Err(e) = stdin.write_all(text.as_bytes())
+        && e.kind() != ErrorKind::BrokenPipe
+    {
+        return Err(e.to_string());
+    }
+
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("exited with {status}"));
+    }
+
+    Ok(())
+}
+
+// called once current conditions and the forecast are done. Prints the banner
+// above everything that was held back while they ran.
+pub async fn alerts(config: &Config) {
+    let mut hits = config
+        .alerts
+        .hits
+        .lock()
+        .map(|mut h| std::mem::take(&mut *h))
+        .unwrap_or_default();
+
+    if hits.is_empty() {
+        config.output.release("");
+        return;
+    }
+
+    hits.sort_by_key(|h| h.rule);
+
+    let mut s = common::title("ALERTS");
+    let mut plain = String::new();
+
+    for hit in hits {
+        let rule = &config.alerts.resolved[hit.rule];
+        let (_, unit, decimals) = history_label(&rule.param).unwrap_or(("", "", 1));
+        let line = format!("{:.decimals$}{unit} at {}", hit.value, hit.place);
+
+        s.push_str(&format!(
+            "{}{}{Reset}: {line}\n",
+            common::Style::warning(),
+            rule.text
+        ));
+        plain.push_str(&format!("{}: {line}\n", rule.text));
+    }
+
+    s.push('\n');
+    config.output.release(&s);
+
+    // after printing, and off the runtime so a slow hook doesn't hold up the
+    // modules still running
+    let Some(hook) = config.alerts.hook.clone() else {
+        return;
+    };
+    let result = tokio::task::spawn_blocking(move || run_hook(&hook, &plain))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r);
+
+    if let Err(e) = result {
+        println!(
+            "{}Could not run the alert hook: {e}{Reset}",
+            common::Style::error()
+        );
+    }
+}
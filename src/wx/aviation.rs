@@ -0,0 +1,159 @@
+use crate::common::Style;
+use crate::common::TermStyle::*;
+use crate::config::{Runway, StationConfig, WxParams};
+use crate::wx::{Entry, FlightRules, History, at_time, ceiling, flight_rules, param_value};
+
+use wxer_lib::*;
+
+// AVIATION --------------------------------------------------------------------
+
+// how many category changes the trend line goes back.
+const MAX_CHANGES: usize = 5;
+
+// knots along and across a heading. Headwind and wind from the right are
+// positive.
+fn components(wind_from: f32, speed: f32, heading: f32) -> (f32, f32) {
+    let angle = (wind_from - heading).to_radians();
+    (speed * angle.cos(), speed * angle.sin())
+}
+
+// the end the wind favors, with its components.
+fn best_end(runway: &Runway, wind_from: f32, speed: f32) -> (String, f32, f32) {
+    let heading = runway.heading as f32;
+    let mut ends = runway.name.split('/').map(str::trim);
+
+    let first = ends.next().unwrap_or(&runway.name).to_string();
+    let (head, cross) = components(wind_from, speed, heading);
+
+    match ends.next() {
+        Some(second) if head < 0. => {
+            let (head, cross) = components(wind_from, speed, heading + 180.);
+            (second.to_string(), head, cross)
+        }
+        _ => (first, head, cross),
+    }
+}
+
+fn runway_line(runway: &Runway, e: &Entry) -> Option<String> {
+    let wind = e.layer(Layer::NearSurface)?.wind()?;
+    let speed = wind.speed.value_in(Knots);
+
+    let label = match &runway.airport {
+        Some(airport) => format!("{airport} "),
+        None => String::new(),
+    };
+
+    // calm, or variable with no direction to work from
+    let Some(direction) = wind.direction.filter(|_| speed > 0.) else {
+        return Some(format!(
+            "  {Bold}{label}Rwy {}:{Reset} {}\n",
+            runway.name,
+            if speed > 0. { "variable" } else { "calm" }
+        ));
+    };
+    let from = direction.degrees() as f32;
+
+    let (end, head, cross) = best_end(runway, from, speed);
+
+    let mut parts = vec![];
+
+    if head >= 0. {
+        parts.push(format!("{head:.0}kts headwind"));
+    } else {
+        parts.push(format!(
+            "{}{:.0}kts tailwind{Reset}",
+            Style::warning(),
+            -head
+        ));
+    }
+
+    let side = if cross >= 0. { "right" } else { "left" };
+    parts.push(format!("{:.0}kts crosswind from the {side}", cross.abs()));
+
+    if let Some(gust) = param_value(e, &WxParams::Gust, Layer::NearSurface)
+        && gust > speed
+    {
+        let (_, _, gust_cross) = best_end(runway, from, gust);
+        parts.push(format!("gusting {:.0}kts across", gust_cross.abs()));
+    }
+
+    Some(format!(
+        "  {Bold}{label}Rwy {end}:{Reset} {}\n",
+        parts.join(", ")
+    ))
+}
+
+// each change of category, oldest first.
+fn category_trend(db: &History) -> Option<String> {
+    let mut changes: Vec<(FlightRules, _)> = vec![];
+
+    for (t, e) in db {
+        let Some(fr) = flight_rules(e) else {
+            continue;
+        };
+        if changes.last().is_none_or(|(last, _)| *last != fr) {
+            changes.push((fr, *t));
+        }
+    }
+
+    let (first, since) = *changes.first()?;
+    if changes.len() == 1 {
+        return Some(format!(
+            "{}{first}{Reset} since {}",
+            first.style(),
+            at_time(since)
+        ));
+    }
+
+    let skip = changes.len().saturating_sub(MAX_CHANGES);
+    let text: Vec<String> = changes[skip..]
+        .iter()
+        .map(|(fr, t)| format!("{}{fr}{Reset} @ {}", fr.style(), at_time(*t)))
+        .collect();
+
+    Some(text.join(" → "))
+}
+
+// altimeter, density altitude, ceiling, runway winds and how the category has
+// changed, for sources set up for it.
+pub fn aviation_panel(db: &History, station: &StationConfig) -> String {
+    if !station.aviation && station.runways.is_empty() {
+        return String::new();
+    }
+
+    let Some((_, e)) = db.last_key_value() else {
+        return String::new();
+    };
+
+    let mut parts = vec![];
+
+    if let Some(altimeter) = e.altimeter {
+        parts.push(format!("Alt {:.2}inHg", altimeter.value_in(Mbar) / 33.8639));
+    }
+    if let Some(da) = param_value(e, &WxParams::DensityAltitude, Layer::NearSurface) {
+        parts.push(format!("DA {da:.0}ft"));
+    }
+    match (&e.skycover, ceiling(e)) {
+        (Some(_), Some(layer)) => parts.push(format!("Ceiling {}ft", layer.height)),
+        (Some(_), None) => parts.push("No ceiling".to_string()),
+        (None, _) => {}
+    }
+
+    let mut s = String::new();
+
+    if !parts.is_empty() {
+        s.push_str(&format!("  {Bold}Aviation:{Reset} {}\n", parts.join(", ")));
+    }
+
+    for runway in &station.runways {
+        if let Some(line) = runway_line(runway, e) {
+            s.push_str(&line);
+        }
+    }
+
+    if let Some(trend) = category_trend(db) {
+        s.push_str(&format!("  {Bold}Category:{Reset} {trend}\n"));
+    }
+
+    s
+}
@@ -0,0 +1,92 @@
+use crate::config::StationConfig;
+use crate::wx::History;
+use crate::wx::observation::{Observation, configured_station, parse_local_datetime};
+
+use std::collections::HashMap;
+
+// DATALOGGER ------------------------------------------------------------------
+
+// Any delimited file with a header row, like the tables a Campbell logger
+// writes. Rows whose timestamp doesn't parse (unit rows and the like) are
+// skipped.
+pub fn csv_history(
+    text: &str,
+    source: &str,
+    station_config: &StationConfig,
+) -> Result<History, String> {
+    if station_config.columns.is_empty() {
+        return Err(format!("{source} does not have any columns configured."));
+    }
+
+    let station = configured_station(source, station_config)?;
+
+    let body = text
+        .lines()
+        .skip(station_config.skip_rows)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(body.as_bytes());
+
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h == name)
+            .ok_or(format!("{source} does not have a column named {name}."))
+    };
+
+    let datetime_column = column(
+        station_config
+            .datetime_column
+            .as_deref()
+            .unwrap_or("Datetime"),
+    )?;
+    let datetime_format = station_config
+        .datetime_format
+        .as_deref()
+        .unwrap_or("%Y-%m-%d %H:%M:%S");
+
+    let columns = station_config
+        .columns
+        .iter()
+        .map(|(name, mapping)| Ok((column(name)?, mapping)))
+        .collect::<Result<HashMap<_, _>, String>>()?;
+
+    let mut db = History::new();
+
+    for record in reader.records() {
+        let Ok(record) = record else {
+            continue;
+        };
+
+        let Some(Ok(date_time)) = record
+            .get(datetime_column)
+            .map(|dt| parse_local_datetime(dt, datetime_format, station_config))
+        else {
+            continue;
+        };
+
+        let mut obs = Observation::default();
+        for (i, mapping) in &columns {
+            // loggers write things like NAN or blanks for missing readings
+            if let Some(Ok(value)) = record.get(*i).map(|v| v.parse::<f32>()) {
+                obs.set(mapping.field, value, mapping.unit)?;
+            }
+        }
+
+        if !obs.is_empty() {
+            db.insert(date_time, obs.build(date_time, station));
+        }
+    }
+
+    if db.is_empty() {
+        return Err(format!("{source} did not have any data."));
+    }
+
+    Ok(db)
+}
@@ -0,0 +1,94 @@
+// DERIVED QUANTITIES ----------------------------------------------------------
+
+// Values worked out from what a source does report. Temperatures are in C and
+// pressures in mb unless the name says otherwise.
+
+// saturation vapor pressure over water (Magnus, WMO coefficients).
+fn vapor_pressure(t: f32) -> f32 {
+    6.112 * ((17.62 * t) / (243.12 + t)).exp()
+}
+
+pub fn relative_humidity(t: f32, dewpoint: f32) -> f32 {
+    (100. * vapor_pressure(dewpoint) / vapor_pressure(t)).min(100.)
+}
+
+// NWS heat index, only defined from 80F up.
+pub fn heat_index(t_f: f32, rh: f32) -> Option<f32> {
+    // the simple formula decides whether the full regression is needed
+    let simple = 0.5 * (t_f + 61. + (t_f - 68.) * 1.2 + rh * 0.094);
+    if (simple + t_f) / 2. < 80. {
+        return None;
+    }
+
+    let mut hi = -42.379 + 2.049_015_2 * t_f + 10.143_331 * rh
+        - 0.224_755_4 * t_f * rh
+        - 0.00683783 * t_f * t_f
+        - 0.05481717 * rh * rh
+        + 0.00122874 * t_f * t_f * rh
+        + 0.00085282 * t_f * rh * rh
+        - 0.00000199 * t_f * t_f * rh * rh;
+
+    if rh < 13. && (80. ..=112.).contains(&t_f) {
+        hi -= ((13. - rh) / 4.) * ((17. - (t_f - 95.).abs()) / 17.).sqrt();
+    } else if rh > 85. && (80. ..=87.).contains(&t_f) {
+        hi += ((rh - 85.) / 10.) * ((87. - t_f) / 5.);
+    }
+
+    Some(hi)
+}
+
+// NWS wind chill, only defined at 50F and below with some wind.
+pub fn wind_chill(t_f: f32, wind_mph: f32) -> Option<f32> {
+    if t_f > 50. || wind_mph <= 3. {
+        return None;
+    }
+
+    let v = wind_mph.powf(0.16);
+    Some(35.74 + 0.6215 * t_f - 35.75 * v + 0.4275 * t_f * v)
+}
+
+// Stull (2011), good to within a degree for ordinary humidities.
+pub fn wet_bulb(t: f32, rh: f32) -> f32 {
+    t * (0.151977 * (rh + 8.313659).sqrt()).atan() + (t + rh).atan() - (rh - 1.676331).atan()
+        + 0.00391838 * rh.powf(1.5) * (0.023101 * rh).atan()
+        - 4.686035
+}
+
+// the Bureau of Meteorology's estimate, for shade and light wind. There's no
+// globe thermometer to account for sun.
+pub fn wbgt(t: f32, dewpoint: f32) -> f32 {
+    0.567 * t + 0.393 * vapor_pressure(dewpoint) + 3.94
+}
+
+// Environment Canada's humidex, unitless.
+pub fn humidex(t: f32, dewpoint: f32) -> f32 {
+    let e = 6.11 * (5417.753 * (1. / 273.16 - 1. / (273.15 + dewpoint))).exp();
+    t + 0.5555 * (e - 10.)
+}
+
+// the temperature the same vapor would saturate over ice instead of water.
+pub fn frost_point(dewpoint: f32) -> f32 {
+    let ln = (vapor_pressure(dewpoint) / 6.112).ln();
+    272.62 * ln / (22.46 - ln)
+}
+
+// feet, from the field elevation and the altimeter setting.
+pub fn pressure_altitude(elevation_ft: f32, altimeter: f32) -> f32 {
+    elevation_ft + 145366.45 * (1. - (altimeter / 1013.25).powf(0.190284))
+}
+
+// feet, using the virtual temperature so humid air counts as thinner.
+pub fn density_altitude(pressure_altitude_ft: f32, t: f32, dewpoint: Option<f32>) -> f32 {
+    let p = 1013.25 * (1. - pressure_altitude_ft / 145366.45).powf(1. / 0.190284);
+
+    let t_k = t + 273.15;
+    let tv_k = match dewpoint {
+        Some(dewpoint) => t_k / (1. - 0.379 * vapor_pressure(dewpoint) / p),
+        None => t_k,
+    };
+
+    let p_inhg = p / 33.8639;
+    let tv_r = tv_k * 9. / 5.;
+
+    145442.16 * (1. - (17.326 * p_inhg / tv_r).powf(0.235))
+}
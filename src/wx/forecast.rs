@@ -1,9 +1,9 @@
 use std::collections::BTreeMap;
 use std::collections::HashMap;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::common;
-use crate::config::Config;
+use crate::config::{Config, LocationView, Service, StationConfig};
 use chrono_tz::America;
 use common::Style;
 use common::TermStyle::*;
@@ -11,7 +11,8 @@ use common::TermStyle::*;
 use crate::wx::*;
 
 use chrono::Weekday::*;
-use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone, Utc};
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use futures::future::{join_all, try_join_all};
 
 use serde::Deserialize;
 
@@ -55,8 +56,8 @@ struct OpenMeteoResponseHourly {
     temperature_2m: Vec<f32>,
     #[serde(rename = "dew_point_2m")]
     dewpoint_2m: Vec<f32>,
-    // #[serde(rename="apparent_temperature")]
-    // feels_like: Vec<f32>,
+    #[serde(rename = "apparent_temperature")]
+    feels_like: Vec<Option<f32>>,
     #[serde(rename = "precipitation_probability")]
     precip_probability: Vec<f32>,
     #[serde(rename = "precipitation")]
@@ -67,12 +68,16 @@ struct OpenMeteoResponseHourly {
     snowfall: Vec<f32>, // get rid of this if it is all zero
     #[serde(rename = "pressure_msl")]
     sea_level_pressure: Vec<f32>,
-    // #[serde(rename = "cloud_cover")]
-    // cloud_cover: Vec<f32>,
+    #[serde(rename = "cloud_cover")]
+    cloud_cover: Vec<Option<f32>>,
     #[serde(rename = "wind_speed_10m")]
     wind_speed_10m: Vec<f32>,
     #[serde(rename = "wind_direction_10m")]
     wind_dir_10m: Vec<u16>,
+    #[serde(rename = "wind_gusts_10m")]
+    wind_gusts_10m: Vec<Option<f32>>,
+    #[serde(rename = "uv_index")]
+    uv_index: Vec<Option<f32>>,
     #[serde(rename = "cape")]
     cape: Vec<f32>,
     #[serde(rename = "windspeed_250hPa")]
@@ -83,18 +88,28 @@ struct OpenMeteoResponseHourly {
     visibility: Vec<f32>,
 }
 
-async fn get_open_meteo(s: &Station) -> Result<OpenMeteoResponse, String> {
+async fn get_open_meteo(
+    s: &Station,
+    location: Option<&LocationView<'_>>,
+    config: &Config,
+) -> Result<OpenMeteoResponse, String> {
+    // already rounded to the precision allowed for Open-Meteo.
     let lat = s.coords.latitude;
     let long = s.coords.longitude;
 
     let url = format!(
-        "https://api.open-meteo.com/v1/forecast?latitude={lat:.2}&longitude={long:.2}&hourly=temperature_2m,dew_point_2m,visibility,apparent_temperature,precipitation_probability,precipitation,rain,snowfall,pressure_msl,cloud_cover,wind_speed_10m,wind_direction_10m,cape,windspeed_250hPa,geopotential_height_500hPa&daily=temperature_2m_max,temperature_2m_min,precipitation_probability_max&temperature_unit=fahrenheit&wind_speed_unit=mph&precipitation_unit=inch"
+        "https://api.open-meteo.com/v1/forecast?latitude={lat}&longitude={long}&hourly=temperature_2m,dew_point_2m,visibility,apparent_temperature,precipitation_probability,precipitation,rain,snowfall,pressure_msl,cloud_cover,wind_speed_10m,wind_direction_10m,wind_gusts_10m,uv_index,cape,windspeed_250hPa,geopotential_height_500hPa&daily=temperature_2m_max,temperature_2m_min,precipitation_probability_max&temperature_unit=fahrenheit&wind_speed_unit=mph&precipitation_unit=inch"
     );
 
     // dbg!(&url);
 
+    if let Some(location) = location {
+        location.audit_request(&Service::OpenMeteo, &url);
+    }
+
     let client = reqwest::Client::new();
 
+    let start = Instant::now();
     let q = client
         .get(&url)
         .timeout(Duration::from_secs(10))
@@ -104,25 +119,23 @@ async fn get_open_meteo(s: &Station) -> Result<OpenMeteoResponse, String> {
     let r = q.map_err(|e| e.to_string())?;
 
     let t = r.text().await.map_err(|e| e.to_string())?;
+    config.timings.network(Modules::Forecast, start, t.len());
 
-    serde_json::from_str(&t).map_err(|e| e.to_string())
-}
+    let start = Instant::now();
+    let response = serde_json::from_str(&t).map_err(|e| e.to_string());
+    config.timings.parse(Modules::Forecast, start);
 
-fn open_meteo_to_entries(open_meteo: OpenMeteoResponse) -> Vec<WxEntryStruct> {
-    let psm_station: &'static Station = Box::leak(Box::new(Station {
-        altitude: Altitude::new_const(30., Meter),
-        coords: Coordinates {
-            latitude: 43.08,
-            longitude: -70.82,
-        },
-        name: "KPSM".to_string(),
-        time_zone: America::New_York,
-    }));
+    response
+}
 
-    let mut entries: Vec<WxEntryStruct> = vec![];
+fn open_meteo_to_entries(open_meteo: OpenMeteoResponse, station: &'static Station) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = vec![];
 
     let hourly = open_meteo.hourly;
 
+    // running total since midnight at the station, for precip_today
+    let mut day_total: Option<(NaiveDate, f32)> = None;
+
     for (idx, date_time) in hourly.time.iter().enumerate() {
         let temperature_2m = Temperature::new(hourly.temperature_2m[idx], Fahrenheit);
         let dewpoint_2m = Temperature::new(hourly.dewpoint_2m[idx], Fahrenheit);
@@ -160,11 +173,24 @@ fn open_meteo_to_entries(open_meteo: OpenMeteoResponse) -> Vec<WxEntryStruct> {
             unknown,
         });
 
+        let local_date = date_time.with_timezone(&station.time_zone).date_naive();
+        let total = match day_total {
+            Some((date, total)) if date == local_date => total + hourly.precip[idx],
+            _ => hourly.precip[idx],
+        };
+        day_total = Some((local_date, total));
+
+        let precip_today = Some(Precip {
+            rain: ProportionalUnit::new(0., Inch),
+            snow: ProportionalUnit::new(0., Inch),
+            unknown: ProportionalUnit::new(total, Inch),
+        });
+
         let mut layers = HashMap::new();
 
         let near_surface = WxEntryLayerStruct {
             layer: Layer::NearSurface,
-            station: psm_station,
+            station,
             wind: Some(wind),
             temperature: Some(temperature_2m),
             dewpoint: Some(dewpoint_2m),
@@ -173,10 +199,10 @@ fn open_meteo_to_entries(open_meteo: OpenMeteoResponse) -> Vec<WxEntryStruct> {
             height_msl: Some(Altitude::new(2.0, Meter)),
         };
 
-        let mut sea_level = WxEntryLayerStruct::new(Layer::SeaLevel, psm_station);
+        let mut sea_level = WxEntryLayerStruct::new(Layer::SeaLevel, station);
         sea_level.pressure = Some(sea_level_pressure);
 
-        let mut layer_250mb = WxEntryLayerStruct::new(Layer::MBAR(250), psm_station);
+        let mut layer_250mb = WxEntryLayerStruct::new(Layer::MBAR(250), station);
         let wind_speed_250mb = Speed::new(hourly.wind_speed_250mb[idx], Mph);
         layer_250mb.wind = Some(Wind {
             direction: None,
@@ -185,7 +211,7 @@ fn open_meteo_to_entries(open_meteo: OpenMeteoResponse) -> Vec<WxEntryStruct> {
 
         // let hght = Altitude::new(hourly.height_500mb[idx], Feet);
         let layer = Layer::MBAR(500);
-        let mut layer_500mb = WxEntryLayerStruct::new(layer, psm_station);
+        let mut layer_500mb = WxEntryLayerStruct::new(layer, station);
         layer_500mb.height_msl = Some(Altitude::new(hourly.height_500mb[idx], Feet)); // convert from feet to meters
 
         layers.insert(Layer::NearSurface, near_surface);
@@ -199,7 +225,7 @@ fn open_meteo_to_entries(open_meteo: OpenMeteoResponse) -> Vec<WxEntryStruct> {
 
         let e = WxEntryStruct {
             date_time: *date_time,
-            station: psm_station,
+            station,
             layers,
             cape,
             skycover: None,
@@ -207,11 +233,17 @@ fn open_meteo_to_entries(open_meteo: OpenMeteoResponse) -> Vec<WxEntryStruct> {
             raw_metar: None,
             precip,
             precip_probability,
-            precip_today: None,
+            precip_today,
             altimeter: None,
         };
 
-        entries.push(e);
+        entries.push(Entry {
+            wx: e,
+            gust: hourly.wind_gusts_10m[idx].map(|g| Speed::new(g, Mph)),
+            cloud_cover: hourly.cloud_cover[idx].map(|c| Fraction::new(c, Percent)),
+            uv_index: hourly.uv_index[idx],
+            apparent_temp: hourly.feels_like[idx].map(|t| Temperature::new(t, Fahrenheit)),
+        });
     }
 
     entries
@@ -229,12 +261,9 @@ fn day_of_week_style<T: TimeZone>(dt: &DateTime<T>) -> String {
     }
 }
 
-async fn forecast_handler(config: &Config) -> Result<String, String> {
-    let mut s = common::title("FORECAST");
-
-    s.push_str("Weather data by Open-Meteo.com (https://open-meteo.com/)\n\n");
-
-    let psm_station: &'static Station = Box::leak(Box::new(Station {
+// used when no locations are configured.
+fn fallback_station() -> &'static Station {
+    Box::leak(Box::new(Station {
         altitude: Altitude::new_const(30., Meter),
         coords: Coordinates {
             latitude: 43.08,
@@ -242,12 +271,51 @@ async fn forecast_handler(config: &Config) -> Result<String, String> {
         },
         name: "KPSM".to_string(),
         time_zone: America::New_York,
-    }));
+    }))
+}
+
+fn location_station(location: &LocationView<'_>) -> Option<&'static Station> {
+    let (latitude, longitude) = location.get_coordinates(&Service::OpenMeteo)?;
+    let altitude = location.altitude().unwrap_or(0.);
+
+    Some(Box::leak(Box::new(Station {
+        altitude: Altitude::new(altitude, Meter),
+        coords: Coordinates {
+            latitude,
+            longitude,
+        },
+        name: location.name().to_string(),
+        time_zone: location.timezone().unwrap_or(America::New_York),
+    })))
+}
+
+async fn forecast_location(
+    config: &Config,
+    station: &'static Station,
+    location: Option<&LocationView<'_>>,
+) -> Result<String, String> {
+    let mut s = String::new();
+
+    if let Some(location) = location
+        && config.localization.is_multi()
+    {
+        s.push_str(&common::location_heading(location.name()));
+    }
 
     let now = Utc::now();
 
-    let r = get_open_meteo(psm_station).await?;
-    let entries = open_meteo_to_entries(r);
+    let r = get_open_meteo(station, location, config).await?;
+
+    let start = Instant::now();
+    let entries = open_meteo_to_entries(r, station);
+    config.timings.parse(Modules::Forecast, start);
+
+    alerts::check_forecast(
+        config,
+        location.map(|l| l.name()),
+        &entries,
+        location.and_then(|l| l.timezone()),
+    );
 
     let mut included = BTreeMap::new();
 
@@ -271,8 +339,16 @@ async fn forecast_handler(config: &Config) -> Result<String, String> {
         included.insert(entry.date_time, entry);
     }
 
+    let start = Instant::now();
+
+    // show times in the location's timezone when we know it
+    let tz = location.and_then(|l| l.timezone());
+
     for (dt, entry) in included {
-        let local_dt: DateTime<Local> = dt.into();
+        let local_dt: DateTime<FixedOffset> = match tz {
+            Some(tz) => dt.with_timezone(&tz).fixed_offset(),
+            None => dt.with_timezone(&Local).fixed_offset(),
+        };
         let day_of_week_style = day_of_week_style(&local_dt);
 
         let prelude = format!(
@@ -285,11 +361,63 @@ async fn forecast_handler(config: &Config) -> Result<String, String> {
             &prelude,
             entry,
             &config.forecast.selected.parameters,
-            false,
+            &StationConfig::default(),
             &BTreeMap::new(),
+            config,
         )?);
     }
 
+    config.timings.render(Modules::Forecast, start);
+
+    Ok(s)
+}
+
+async fn forecast_handler(config: &Config) -> Result<String, String> {
+    let mut s = common::title("FORECAST");
+
+    s.push_str("Weather data by Open-Meteo.com (https://open-meteo.com/)\n\n");
+
+    let locations = config.localization.selected();
+
+    let mut targets: Vec<_> = locations
+        .iter()
+        .map(|location| (location_station(location), Some(location)))
+        .collect();
+
+    if targets.is_empty() {
+        targets.push((Some(fallback_station()), None));
+    }
+
+    let futures = targets.into_iter().map(|(station, location)| async move {
+        let Some(station) = station else {
+            let name = location.map_or("", |l| l.name());
+            return Ok(format!("{name}: location not shared with openmeteo\n"));
+        };
+        forecast_location(config, station, location).await
+    });
+
+    let tafs = config
+        .forecast
+        .tafs
+        .iter()
+        .map(|x| taf::taf_section(x, config));
+
+    // the TAFs don't depend on Open-Meteo, so they're still shown if it fails
+    let (locations, tafs) = futures::join!(try_join_all(futures), join_all(tafs));
+
+    match locations {
+        Ok(locations) => s.push_str(&locations.join("\n")),
+        Err(e) if !tafs.is_empty() => {
+            s.push_str(&format!("{}{}{Reset}\n", Style::error(), e.trim_end()))
+        }
+        Err(e) => return Err(e),
+    }
+
+    for taf in tafs {
+        s.push('\n');
+        s.push_str(&taf);
+    }
+
     Ok(s)
 }
 
@@ -301,11 +429,9 @@ pub async fn forecast(config: &Config) {
     }
 
     match forecast_handler(config).await {
-        Ok(s) => {
-            println!("{}", s)
-        }
-        Err(e) => {
-            println!("{}{}", common::title("FORECAST"), e)
-        }
+        Ok(s) => config.output.print(&s),
+        Err(e) => config
+            .output
+            .print(&format!("{}{}", common::title("FORECAST"), e)),
     }
 }
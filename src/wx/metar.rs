@@ -0,0 +1,674 @@
+use crate::config::{ObsField, ScaleUnit, StationConfig};
+use crate::wx::observation::{Observation, configured_station};
+use crate::wx::present_weather::WxCode;
+use crate::wx::{Entry, History};
+
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+
+use wxer_lib::*;
+
+// METAR -----------------------------------------------------------------------
+
+// aviationweather.gov's data api, {station} is replaced with the station id.
+pub const DEFAULT_METAR_URL: &str =
+    "https://aviationweather.gov/api/data/metar?ids={station}&format=raw&hours=24";
+
+// DDHHMM or DDHH, resolved to whichever month puts it closest to now. Hour 24
+// is midnight at the end of the day, which TAFs use.
+pub fn day_time(s: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if !s.chars().all(|c| c.is_ascii_digit()) || !(s.len() == 4 || s.len() == 6) {
+        return None;
+    }
+
+    let day: u32 = s[0..2].parse().ok()?;
+    let hour: i64 = s[2..4].parse().ok()?;
+    let minute: i64 = s.get(4..6).map_or(Some(0), |m| m.parse().ok())?;
+
+    if hour > 24 || minute > 59 {
+        return None;
+    }
+
+    let this_month = NaiveDate::from_ymd_opt(now.year(), now.month(), 1)?;
+
+    [
+        this_month.checked_sub_months(Months::new(1)),
+        Some(this_month),
+        this_month.checked_add_months(Months::new(1)),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|month| month.with_day(day))
+    .filter_map(|date| date.and_hms_opt(0, 0, 0))
+    .map(|dt| dt.and_utc() + Duration::hours(hour) + Duration::minutes(minute))
+    .min_by_key(|dt| (*dt - now).abs())
+}
+
+// "M05" is -5
+fn signed_temp(s: &str) -> Option<f32> {
+    match s.strip_prefix('M') {
+        Some(t) => t.parse::<f32>().ok().map(|t| -t),
+        None => s.parse().ok(),
+    }
+}
+
+pub struct WindGroup {
+    pub direction: Option<u16>,
+    pub speed: f32,
+    pub gust: Option<f32>,
+    pub unit: ScaleUnit,
+}
+
+// 27015G25KT, VRB03KT, 00000KT, 09005MPS
+pub fn parse_wind(t: &str) -> Option<WindGroup> {
+    let (t, unit) = if let Some(t) = t.strip_suffix("KT") {
+        (t, ScaleUnit::Kts)
+    } else if let Some(t) = t.strip_suffix("MPS") {
+        (t, ScaleUnit::Ms)
+    } else if let Some(t) = t.strip_suffix("KMH") {
+        (t, ScaleUnit::Kmh)
+    } else {
+        return None;
+    };
+
+    if t.len() < 5 {
+        return None;
+    }
+
+    let (dir, rest) = t.split_at(3);
+    let direction = match dir {
+        "VRB" => None,
+        d => Some(d.parse::<u16>().ok()?),
+    };
+
+    let (speed, gust) = match rest.split_once('G') {
+        Some((s, g)) => (s, Some(g.parse::<f32>().ok()?)),
+        None => (rest, None),
+    };
+    let speed: f32 = speed.parse().ok()?;
+
+    Some(WindGroup {
+        // calm has no direction
+        direction: direction.filter(|_| speed > 0.),
+        speed,
+        gust,
+        unit,
+    })
+}
+
+// "1/2" or "2"
+fn fraction(s: &str) -> Option<f32> {
+    match s.split_once('/') {
+        Some((n, d)) => {
+            let d: f32 = d.parse().ok()?;
+            (d != 0.).then_some(n.parse::<f32>().ok()? / d)
+        }
+        None => s.parse().ok(),
+    }
+}
+
+// 10SM, 1/2SM, 1 1/2SM, M1/4SM, P6SM, 9999, 0800. Returns the visibility and
+// how many tokens it took.
+pub fn parse_visibility(tokens: &[&str]) -> Option<(f32, ScaleUnit, usize)> {
+    let first = *tokens.first()?;
+
+    if let Some(v) = first.strip_suffix("SM") {
+        let v = v.trim_start_matches(['M', 'P']);
+        return Some((fraction(v)?, ScaleUnit::Mi, 1));
+    }
+
+    // a whole number of miles followed by a fraction
+    if first.len() == 1
+        && let Some(frac) = tokens.get(1).and_then(|t| t.strip_suffix("SM"))
+        && frac.contains('/')
+    {
+        let whole: f32 = first.parse().ok()?;
+        return Some((whole + fraction(frac)?, ScaleUnit::Mi, 2));
+    }
+
+    if first.len() == 4 && first.chars().all(|c| c.is_ascii_digit()) {
+        let m: f32 = first.parse().ok()?;
+        // 9999 is 10km or more
+        let m = if m == 9999. { 10000. } else { m };
+        return Some((m, ScaleUnit::M, 1));
+    }
+
+    None
+}
+
+pub enum CloudGroup {
+    Clear,
+    Layer(CloudLayer),
+}
+
+// FEW020, BKN035CB, OVC///, VV002, CLR, SKC, NSC, NCD
+pub fn parse_cloud(t: &str) -> Option<CloudGroup> {
+    use CloudLayerCoverage::*;
+
+    if matches!(t, "CLR" | "SKC" | "NSC" | "NCD") {
+        return Some(CloudGroup::Clear);
+    }
+
+    let (coverage, rest) = if let Some(r) = t.strip_prefix("FEW") {
+        (Few, r)
+    } else if let Some(r) = t.strip_prefix("SCT") {
+        (Scattered, r)
+    } else if let Some(r) = t.strip_prefix("BKN") {
+        (Broken, r)
+    } else if let Some(r) = t.strip_prefix("OVC") {
+        (Overcast, r)
+    } else if let Some(r) = t.strip_prefix("VV") {
+        // an indefinite ceiling counts as overcast at the vertical visibility
+        (Overcast, r)
+    } else {
+        return None;
+    };
+
+    let rest = rest.trim_end_matches("CB").trim_end_matches("TCU");
+    if rest.len() != 3 {
+        return None;
+    }
+
+    let hundreds: u32 = rest.parse().ok()?;
+
+    Some(CloudGroup::Layer(CloudLayer {
+        coverage,
+        height: hundreds * 100,
+    }))
+}
+
+// -SHRA, +TSRAGR, VCSH, BR. Checks every code rather than looking for
+// substrings, so station ids and remarks aren't mistaken for weather.
+pub fn is_wx_code(t: &str) -> bool {
+    WxCode::parse(t).is_some()
+}
+
+// The groups METARs and TAFs share: wind, visibility, weather and clouds.
+#[derive(Default)]
+pub struct ReportBody {
+    pub obs: Observation,
+    pub clouds: Vec<CloudLayer>,
+    pub clear: bool,
+    pub wx: Vec<String>,
+}
+
+impl ReportBody {
+    // reads one group from the start of tokens, returning how many tokens it
+    // used, or 0 if it isn't a group we know.
+    pub fn group(&mut self, tokens: &[&str]) -> Result<usize, String> {
+        let Some(&t) = tokens.first() else {
+            return Ok(0);
+        };
+
+        if let Some(w) = parse_wind(t) {
+            self.obs.set(ObsField::WindSpeed, w.speed, Some(w.unit))?;
+            if let Some(gust) = w.gust {
+                self.obs.set(ObsField::WindGust, gust, Some(w.unit))?;
+            }
+            if let Some(dir) = w.direction {
+                self.obs.set(ObsField::WindDirection, dir as f32, None)?;
+            }
+            return Ok(1);
+        }
+
+        if t == "CAVOK" {
+            self.obs
+                .set(ObsField::Visibility, 10., Some(ScaleUnit::Km))?;
+            self.clear = true;
+            return Ok(1);
+        }
+
+        if let Some((v, unit, used)) = parse_visibility(tokens) {
+            self.obs.set(ObsField::Visibility, v, Some(unit))?;
+            return Ok(used);
+        }
+
+        if let Some(c) = parse_cloud(t) {
+            match c {
+                CloudGroup::Clear => self.clear = true,
+                CloudGroup::Layer(l) => self.clouds.push(l),
+            }
+            return Ok(1);
+        }
+
+        if is_wx_code(t) {
+            self.wx.push(t.to_string());
+            return Ok(1);
+        }
+
+        Ok(0)
+    }
+
+    pub fn skycover(&mut self) -> Option<SkyCoverage> {
+        if !self.clouds.is_empty() {
+            Some(SkyCoverage::Cloudy(std::mem::take(&mut self.clouds)))
+        } else if self.clear {
+            Some(SkyCoverage::Clear)
+        } else {
+            None
+        }
+    }
+
+    // the most covered layer, in oktas, as a percentage.
+    fn cloud_cover(&self) -> Option<f32> {
+        use CloudLayerCoverage::*;
+
+        let oktas = self
+            .clouds
+            .iter()
+            .map(|l| match l.coverage {
+                Few => 2,
+                Scattered => 4,
+                Broken => 7,
+                Overcast => 8,
+            })
+            .max();
+
+        match oktas {
+            Some(o) => Some(o as f32 / 8. * 100.),
+            None if self.clear => Some(0.),
+            None => None,
+        }
+    }
+
+    pub fn build(mut self, date_time: DateTime<Utc>, station: &'static Station) -> Entry {
+        let mut entry = self.obs.build(date_time, station);
+        entry.cloud_cover = self.cloud_cover().map(|c| Fraction::new(c, Percent));
+        entry.skycover = self.skycover();
+        entry.wx_codes = Some(std::mem::take(&mut self.wx));
+        entry
+    }
+}
+
+// One report, "METAR KPSM 182053Z 27008KT 10SM CLR 12/M03 A3012 RMK ...".
+// Remarks are only read for the precise temperatures and sea level pressure.
+pub fn decode_metar(
+    raw: &str,
+    station: &'static Station,
+    now: DateTime<Utc>,
+) -> Result<(DateTime<Utc>, Entry), String> {
+    let tokens: Vec<&str> = raw
+        .split_ascii_whitespace()
+        .skip_while(|t| matches!(*t, "METAR" | "SPECI"))
+        .collect();
+
+    let (body, remarks) = match tokens.iter().position(|t| *t == "RMK") {
+        Some(i) => (&tokens[..i], &tokens[i + 1..]),
+        None => (&tokens[..], &[][..]),
+    };
+
+    let date_time = body
+        .get(1)
+        .and_then(|t| t.strip_suffix('Z'))
+        .and_then(|t| day_time(t, now))
+        .ok_or(format!("Could not read the time of \"{raw}\"."))?;
+
+    let mut report = ReportBody::default();
+
+    let mut i = 2;
+    while i < body.len() {
+        let t = body[i];
+
+        let used = report.group(&body[i..])?;
+        if used > 0 {
+            i += used;
+            continue;
+        }
+
+        if let Some((temp, dew)) = t.split_once('/')
+            && (temp.is_empty() || signed_temp(temp).is_some())
+        {
+            if let Some(temp) = signed_temp(temp) {
+                report
+                    .obs
+                    .set(ObsField::Temperature, temp, Some(ScaleUnit::C))?;
+            }
+            if let Some(dew) = signed_temp(dew) {
+                report
+                    .obs
+                    .set(ObsField::Dewpoint, dew, Some(ScaleUnit::C))?;
+            }
+        } else if let Some(a) = t.strip_prefix('A').and_then(|a| a.parse::<f32>().ok()) {
+            report
+                .obs
+                .set(ObsField::Altimeter, a / 100., Some(ScaleUnit::InHg))?;
+        } else if let Some(q) = t.strip_prefix('Q').and_then(|q| q.parse::<f32>().ok()) {
+            report
+                .obs
+                .set(ObsField::Altimeter, q, Some(ScaleUnit::Mb))?;
+        }
+        // anything else, like AUTO, RVR and variable wind, is skipped
+
+        i += 1;
+    }
+
+    for t in remarks {
+        // T01221028 is 12.2C and -2.8C
+        if t.len() == 9
+            && let Some(temps) = t.strip_prefix('T')
+            && temps.chars().all(|c| c.is_ascii_digit())
+        {
+            let tenths = |s: &str| {
+                let v = s[1..].parse::<f32>().ok()? / 10.;
+                Some(if s.starts_with('1') { -v } else { v })
+            };
+
+            if let Some(temp) = tenths(&temps[0..4]) {
+                report
+                    .obs
+                    .set(ObsField::Temperature, temp, Some(ScaleUnit::C))?;
+            }
+            if let Some(dew) = tenths(&temps[4..8]) {
+                report
+                    .obs
+                    .set(ObsField::Dewpoint, dew, Some(ScaleUnit::C))?;
+            }
+        } else if t.len() == 5
+            && let Some(p) = t.strip_prefix('P').and_then(|p| p.parse::<f32>().ok())
+        {
+            // P0012 is 0.12in in the last hour
+            report
+                .obs
+                .set(ObsField::Precip, p / 100., Some(ScaleUnit::In))?;
+        } else if let Some(slp) = t.strip_prefix("SLP").and_then(|s| s.parse::<f32>().ok()) {
+            // the hundreds are left off, 132 is 1013.2 and 982 is 998.2
+            let slp = slp / 10.;
+            let slp = if slp < 50. { slp + 1000. } else { slp + 900. };
+            report
+                .obs
+                .set(ObsField::SeaLevelPressure, slp, Some(ScaleUnit::Mb))?;
+        }
+    }
+
+    let mut entry = report.build(date_time, station);
+    entry.raw_metar = Some(raw.to_string());
+
+    Ok((date_time, entry))
+}
+
+// every report for the station in a text response or file. Lines that aren't
+// this station's reports, like the timestamps in NOAA's files, are skipped.
+pub fn metar_history(
+    text: &str,
+    source: &str,
+    station_config: &StationConfig,
+) -> Result<History, String> {
+    let station_id = station_config.station.as_deref().unwrap_or(source);
+    let station = configured_station(station_id, station_config)?;
+    let now = Utc::now();
+
+    let mut db = History::new();
+    let mut error = None;
+
+    for line in text.lines().map(str::trim) {
+        let id = line
+            .split_ascii_whitespace()
+            .find(|t| !matches!(*t, "METAR" | "SPECI"));
+
+        if id != Some(station_id) {
+            continue;
+        }
+
+        // one bad report shouldn't lose the rest
+        match decode_metar(line, station, now) {
+            // a correction replaces the report it corrects
+            Ok((date_time, entry)) => {
+                db.insert(date_time, entry);
+            }
+            Err(e) => error = Some(e),
+        }
+    }
+
+    if db.is_empty() {
+        return Err(error.unwrap_or(format!("No METARs for {station_id}.")));
+    }
+
+    Ok(db)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    fn station() -> &'static Station {
+        configured_station("KPSM", &StationConfig::default()).unwrap()
+    }
+
+    fn visibility(e: &Entry) -> f32 {
+        e.layers[&Layer::NearSurface]
+            .visibility
+            .unwrap()
+            .value_in(Mile)
+    }
+
+    #[test]
+    fn day_time_in_this_month() {
+        let now = at(2026, 10, 18, 21, 0);
+        assert_eq!(day_time("182053", now), Some(at(2026, 10, 18, 20, 53)));
+        assert_eq!(day_time("1818", now), Some(at(2026, 10, 18, 18, 0)));
+    }
+
+    #[test]
+    fn day_time_rolls_over_months() {
+        // just after midnight on the 1st, the 31st is last month
+        let now = at(2026, 11, 1, 0, 30);
+        assert_eq!(day_time("312350", now), Some(at(2026, 10, 31, 23, 50)));
+
+        // and late on the 31st, the 1st is next month
+        let now = at(2026, 10, 31, 23, 0);
+        assert_eq!(day_time("010100", now), Some(at(2026, 11, 1, 1, 0)));
+
+        // across the new year
+        let now = at(2027, 1, 1, 0, 30);
+        assert_eq!(day_time("311800", now), Some(at(2026, 12, 31, 18, 0)));
+    }
+
+    #[test]
+    fn day_time_hour_24() {
+        let now = at(2026, 10, 18, 21, 0);
+        assert_eq!(day_time("1924", now), Some(at(2026, 10, 20, 0, 0)));
+
+        // the end of the last day of the month is the 1st
+        let now = at(2026, 10, 31, 12, 0);
+        assert_eq!(day_time("3124", now), Some(at(2026, 11, 1, 0, 0)));
+    }
+
+    #[test]
+    fn day_time_rejects() {
+        let now = at(2026, 10, 18, 21, 0);
+        for s in ["1925", "182060", "18205", "18205Z", "321200", ""] {
+            assert_eq!(day_time(s, now), None, "{s}");
+        }
+    }
+
+    #[test]
+    fn visibility_groups() {
+        use ScaleUnit::*;
+
+        let vis = |tokens: &[&str]| parse_visibility(tokens);
+
+        assert!(matches!(vis(&["10SM", "FEW020"]), Some((10., Mi, 1))));
+        assert!(matches!(vis(&["P6SM", "SKC"]), Some((6., Mi, 1))));
+        assert!(matches!(vis(&["1/2SM", "FG"]), Some((0.5, Mi, 1))));
+        assert!(matches!(vis(&["1", "1/2SM", "BR"]), Some((1.5, Mi, 2))));
+        assert!(matches!(vis(&["M1/4SM", "FG"]), Some((0.25, Mi, 1))));
+        assert!(matches!(vis(&["9999", "NSC"]), Some((10000., M, 1))));
+        assert!(matches!(vis(&["0800", "FG"]), Some((800., M, 1))));
+
+        // a lone digit without a fraction after it isn't visibility
+        assert!(vis(&["1", "BKN008"]).is_none());
+        assert!(vis(&["A2992"]).is_none());
+    }
+
+    #[test]
+    fn wind_groups() {
+        let w = parse_wind("27015G25KT").unwrap();
+        assert_eq!((w.direction, w.speed, w.gust), (Some(270), 15., Some(25.)));
+        assert!(matches!(w.unit, ScaleUnit::Kts));
+
+        let w = parse_wind("VRB03KT").unwrap();
+        assert_eq!((w.direction, w.speed, w.gust), (None, 3., None));
+
+        // calm has no direction
+        let w = parse_wind("00000KT").unwrap();
+        assert_eq!((w.direction, w.speed), (None, 0.));
+
+        let w = parse_wind("09005MPS").unwrap();
+        assert_eq!((w.direction, w.speed), (Some(90), 5.));
+        assert!(matches!(w.unit, ScaleUnit::Ms));
+
+        assert!(parse_wind("250V310").is_none());
+        assert!(parse_wind("KT").is_none());
+    }
+
+    #[test]
+    fn cloud_groups() {
+        use CloudLayerCoverage::*;
+
+        let layer = |t| match parse_cloud(t) {
+            Some(CloudGroup::Layer(l)) => Some((l.coverage, l.height)),
+            _ => None,
+        };
+
+        assert_eq!(layer("FEW020"), Some((Few, 2000)));
+        assert_eq!(layer("BKN035CB"), Some((Broken, 3500)));
+        assert_eq!(layer("SCT050TCU"), Some((Scattered, 5000)));
+        // an indefinite ceiling is overcast at the vertical visibility
+        assert_eq!(layer("VV002"), Some((Overcast, 200)));
+
+        for t in ["CLR", "SKC", "NSC", "NCD"] {
+            assert!(matches!(parse_cloud(t), Some(CloudGroup::Clear)), "{t}");
+        }
+
+        // the height is missing when an automated station can't tell
+        assert!(parse_cloud("OVC///").is_none());
+        assert!(parse_cloud("BKN").is_none());
+    }
+
+    #[test]
+    fn decodes_metar_with_remarks() {
+        let raw = "METAR KPSM 182053Z 27018G28KT 1 1/2SM -SHRA BR BKN008 OVC015 12/M03 A2992 \
+                   RMK AO2 SLP132 P0003 T01221028";
+        let (date_time, e) = decode_metar(raw, station(), at(2026, 10, 18, 21, 0)).unwrap();
+
+        assert_eq!(date_time, at(2026, 10, 18, 20, 53));
+        assert_eq!(e.raw_metar.as_deref(), Some(raw));
+
+        let surface = &e.layers[&Layer::NearSurface];
+        // the remarks' tenths win over the whole degrees in the body
+        assert!(close(
+            surface.temperature.unwrap().value_in(Fahrenheit),
+            53.96
+        ));
+        assert!(close(surface.dewpoint.unwrap().value_in(Fahrenheit), 26.96));
+
+        let wind = surface.wind.unwrap();
+        assert_eq!(wind.direction.map(|d| d.degrees()), Some(270));
+        assert!(close(wind.speed.value_in(Knots), 18.));
+        assert!(close(e.gust.unwrap().value_in(Knots), 28.));
+
+        assert!(close(visibility(&e), 1.5));
+        assert_eq!(
+            e.wx_codes.as_deref(),
+            Some(&["-SHRA".to_string(), "BR".to_string()][..])
+        );
+
+        match &e.skycover {
+            Some(SkyCoverage::Cloudy(layers)) => {
+                let heights: Vec<u32> = layers.iter().map(|l| l.height).collect();
+                assert_eq!(heights, [800, 1500]);
+            }
+            other => panic!("{other:?}"),
+        }
+        assert!(close(e.cloud_cover.unwrap().value_in(Percent), 100.));
+
+        let slp = e.layers[&Layer::SeaLevel].pressure.unwrap();
+        assert!(close(slp.value_in(Mbar), 1013.2));
+        assert!(close(e.altimeter.unwrap().value_in(Mbar), 1013.21));
+        assert!(close(e.precip.unwrap().rain.value_in(Inch), 0.03));
+    }
+
+    #[test]
+    fn sea_level_pressure_below_1000() {
+        let raw = "KPSM 182053Z 18025G40KT 3SM +RA OVC010 09/08 A2901 RMK SLP982 T00890078";
+        let (_, e) = decode_metar(raw, station(), at(2026, 10, 18, 21, 0)).unwrap();
+
+        let slp = e.layers[&Layer::SeaLevel].pressure.unwrap();
+        assert!(close(slp.value_in(Mbar), 998.2));
+    }
+
+    #[test]
+    fn negative_remark_temperatures() {
+        let raw = "SPECI KPSM 180312Z 32012KT 1/4SM +SN VV002 M07/M09 A3001 RMK T10671089";
+        let (_, e) = decode_metar(raw, station(), at(2026, 10, 18, 4, 0)).unwrap();
+
+        let surface = &e.layers[&Layer::NearSurface];
+        assert!(close(
+            surface.temperature.unwrap().value_in(Fahrenheit),
+            19.94
+        ));
+        assert!(close(surface.dewpoint.unwrap().value_in(Fahrenheit), 15.98));
+        assert!(close(visibility(&e), 0.25));
+    }
+
+    #[test]
+    fn decodes_cavok() {
+        let raw = "METAR EGLL 182050Z AUTO 24012KT CAVOK 14/09 Q1015 NOSIG";
+        let (_, e) = decode_metar(raw, station(), at(2026, 10, 18, 21, 0)).unwrap();
+
+        // 10km or more
+        assert!(close(visibility(&e), 6.21));
+        assert!(matches!(e.skycover, Some(SkyCoverage::Clear)));
+        assert!(close(e.cloud_cover.unwrap().value_in(Percent), 0.));
+        assert!(close(e.altimeter.unwrap().value_in(Mbar), 1015.));
+        assert_eq!(e.wx_codes.as_deref(), Some(&[][..]));
+    }
+
+    #[test]
+    fn decodes_nsc() {
+        let raw = "METAR LFPG 182100Z 21008KT 9999 NSC 13/10 Q1012 NOSIG";
+        let (_, e) = decode_metar(raw, station(), at(2026, 10, 18, 21, 0)).unwrap();
+
+        assert!(close(visibility(&e), 6.21));
+        assert!(matches!(e.skycover, Some(SkyCoverage::Clear)));
+        assert!(close(e.altimeter.unwrap().value_in(Mbar), 1012.));
+    }
+
+    #[test]
+    fn unreadable_time() {
+        let raw = "KPSM NIL";
+        assert!(decode_metar(raw, station(), at(2026, 10, 18, 21, 0)).is_err());
+    }
+
+    #[test]
+    fn history_skips_bad_reports() {
+        let text = "2026/10/18 20:53\n\
+                    KPSM 181753Z 27008KT 10SM CLR 12/M03 A3012\n\
+                    KPSM NIL\n\
+                    KBOS 181754Z 28012KT 10SM FEW050 13/M02 A3010\n\
+                    SPECI KPSM 181812Z 27010KT 10SM FEW045 12/M03 A3012\n";
+
+        let db = metar_history(text, "KPSM", &StationConfig::default()).unwrap();
+        assert_eq!(db.len(), 2);
+    }
+
+    #[test]
+    fn history_without_good_reports() {
+        let config = StationConfig::default();
+
+        let e = metar_history("KPSM NIL\n", "KPSM", &config).unwrap_err();
+        assert!(e.contains("KPSM NIL"), "{e}");
+
+        let e = metar_history("KBOS 181754Z 28012KT 10SM", "KPSM", &config).unwrap_err();
+        assert_eq!(e, "No METARs for KPSM.");
+    }
+}
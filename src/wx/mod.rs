@@ -1,14 +1,27 @@
+pub mod alerts;
+pub mod aviation;
+pub mod datalogger;
+pub mod derived;
 pub mod forecast;
+pub mod metar;
+pub mod observation;
+pub mod present_weather;
+pub mod pws;
+pub mod taf;
 pub mod tele;
 pub mod weather;
+pub mod wxer;
 
 use crate::common;
 use common::Style;
 use common::TermStyle::*;
+use present_weather::{WxCode, wx_style};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
+use chrono_tz::Tz;
 use std::collections::BTreeMap;
 use std::fmt;
+use std::ops::{Deref, DerefMut};
 use wxer_lib::WxEntryLayer;
 
 use wxer_lib::*;
@@ -22,16 +35,6 @@ struct WeatherData {
     style: String,
 }
 
-#[derive(Debug, PartialEq)]
-enum WxCategory {
-    Snow,
-    Rain,
-    Severe,
-    Fire,
-    Fog,
-    None,
-}
-
 impl fmt::Display for WeatherData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if self.is_none() {
@@ -67,79 +70,37 @@ impl WeatherData {
     }
 }
 
-fn format_wx(o: Option<Vec<String>>) -> WeatherData {
-    let mut text: String;
-    let mut style: String;
-
-    match o {
-        None => {
-            return WeatherData::none();
-        }
-        Some(v) => {
-            let mut wx_types = vec![];
-
-            for wx in &v {
-                let c = if wx.contains("FU") | wx.contains("VA") {
-                    WxCategory::Fire
-                } else if wx.contains("GR")
-                    | wx.contains("TS")
-                    | wx.contains("DU")
-                    | wx.contains("SA")
-                    | wx.contains("SQ")
-                    | wx.contains("DS")
-                    | wx.contains("SS")
-                    | wx.contains("FC")
-                {
-                    WxCategory::Severe
-                } else if wx.contains("SN")
-                    | wx.contains("SG")
-                    | wx.contains("GS")
-                    | wx.contains("PL")
-                    | wx.contains("IC")
-                {
-                    WxCategory::Snow
-                } else if wx.contains("DZ") | wx.contains("RA") | wx.contains("UP") {
-                    WxCategory::Rain
-                } else if wx.contains("FG")
-                    | wx.contains("BR")
-                    | wx.contains("HZ")
-                    | wx.contains("PY")
-                {
-                    WxCategory::Fog
-                } else {
-                    WxCategory::None
-                };
-
-                wx_types.push(c);
-            }
-
-            if wx_types.contains(&WxCategory::Fire) {
-                style = Style::string(&[RedBg, White, Bold]);
-            } else if wx_types.contains(&WxCategory::Severe) {
-                style = Style::string(&[YellowBg, Black, Bold]);
-            } else if wx_types.contains(&WxCategory::Snow) {
-                style = Style::string(&[WhiteBg, Blue, Bold]);
-            } else if wx_types.contains(&WxCategory::Rain) {
-                style = Style::string(&[BlueBg, Black, Bold]);
-            } else if wx_types.contains(&WxCategory::Fog) {
-                style = Style::string(&[WhiteBg, Black, Bold]);
-            } else {
-                style = Style::string(&[Reset]);
-            }
+// codes that don't parse are shown as they are, and don't affect the color.
+fn format_wx(codes: Option<&[String]>, ctx: &LineContext) -> WeatherData {
+    let Some(codes) = codes else {
+        return WeatherData::none();
+    };
 
-            text = v.join(" ");
-        }
+    if codes.is_empty() {
+        return WeatherData {
+            title: "".into(),
+            text: "No WX".into(),
+            style: Style::string(&[Bold]),
+        };
     }
 
-    if text.is_empty() {
-        text.push_str("No WX");
-        style = Style::string(&[Bold])
-    }
+    let parsed: Vec<Option<WxCode>> = codes.iter().map(|c| WxCode::parse(c)).collect();
+
+    let text = if ctx.config.weather.describe_wx {
+        codes
+            .iter()
+            .zip(&parsed)
+            .map(|(raw, code)| code.as_ref().map_or(raw.clone(), |c| c.describe()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    } else {
+        codes.join(" ")
+    };
 
     WeatherData {
         title: "".into(),
         text,
-        style,
+        style: wx_style(&parsed.into_iter().flatten().collect::<Vec<_>>()),
     }
 }
 
@@ -156,69 +117,140 @@ enum Trend {
 
 use Trend::*;
 
-fn db_reverse_time(
-    db: &BTreeMap<DateTime<Utc>, WxStructDeserialized>,
-    duration: chrono::Duration,
-) -> Result<&WxStructDeserialized, String> {
-    let latest = db
-        .last_key_value()
-        .ok_or(String::from("database has nothing"))?;
+// observation timestamps jitter, so a sample this close to the time we want
+// is used as is.
+const TREND_TOLERANCE: chrono::Duration = chrono::Duration::minutes(10);
+// otherwise interpolate, but only between samples this close together.
+const MAX_INTERPOLATION_GAP: chrono::Duration = chrono::Duration::hours(2);
+
+// the value at `target`, and the time it actually represents.
+fn db_value_at<'a, F: FnMut(&'a Entry) -> Option<f32>>(
+    db: &'a History,
+    target: DateTime<Utc>,
+    get_field: &mut F,
+) -> Option<(DateTime<Utc>, f32)> {
+    let before = db
+        .range(..=target)
+        .rev()
+        .find_map(|(t, d)| get_field(d).map(|v| (*t, v)));
+    let after = db
+        .range(target..)
+        .find_map(|(t, d)| get_field(d).map(|v| (*t, v)));
+
+    let nearest = [before, after]
+        .into_iter()
+        .flatten()
+        .min_by_key(|(t, _)| (*t - target).abs());
+
+    if let Some((t, v)) = nearest
+        && (t - target).abs() <= TREND_TOLERANCE
+    {
+        return Some((t, v));
+    }
+
+    let ((t0, v0), (t1, v1)) = (before?, after?);
+
+    if t1 - t0 > MAX_INTERPOLATION_GAP {
+        return None;
+    }
+
+    let frac = (target - t0).as_seconds_f32() / (t1 - t0).as_seconds_f32();
+    Some((target, v0 + (v1 - v0) * frac))
+}
+
+fn span_str(span: chrono::Duration) -> String {
+    let minutes = span.num_minutes();
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m}m"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h{m}m"),
+    }
+}
+
+struct TrendReport {
+    trend: Trend,
+    // change over the change window, and the span it was actually measured over
+    change: Option<(f32, chrono::Duration)>,
+}
 
-    let dt_past = *latest.0 - duration;
-    let past_entry = db
-        .get(&dt_past)
-        .ok_or(String::from("Past entry was not found."))?;
+impl TrendReport {
+    fn unknown() -> Self {
+        TrendReport {
+            trend: UnknownChange,
+            change: None,
+        }
+    }
 
-    Ok(past_entry)
+    // e.g. " ↗" or " ↗ +3.2F/2h"
+    fn text(&self, unit: &str, show_change: bool) -> String {
+        match self.change {
+            Some((delta, span)) if show_change => {
+                format!("{} {delta:+.1}{unit}/{}", self.trend, span_str(span))
+            }
+            _ => self.trend.to_string(),
+        }
+    }
 }
 
 impl Trend {
-    fn from_db_inner<'a, F: FnMut(&'a WxStructDeserialized) -> Option<f32>>(
-        db: &'a BTreeMap<DateTime<Utc>, WxStructDeserialized>,
+    fn from_db_inner<'a, F: FnMut(&'a Entry) -> Option<f32>>(
+        db: &'a History,
         mut get_field: F,
-        change_criteria: (chrono::Duration, f32),
-        rapid_criteria: (chrono::Duration, f32, chrono::Duration, f32),
-    ) -> Result<Trend, ()> {
-        let latest = db.last_key_value().ok_or(())?;
-
-        let ref_1 = db_reverse_time(db, change_criteria.0).map_err(|_| ())?;
-        let ref_2 = db_reverse_time(db, rapid_criteria.0).map_err(|_| ())?;
-        let ref_3 = db_reverse_time(db, rapid_criteria.2).map_err(|_| ())?;
-
-        let ref_1_change = get_field(latest.1).ok_or(())? - get_field(ref_1).ok_or(())?;
-        let ref_2_change = get_field(latest.1).ok_or(())? - get_field(ref_2).ok_or(())?;
-        let ref_3_change = get_field(latest.1).ok_or(())? - get_field(ref_3).ok_or(())?;
-
-        // dbg!(ref_1);
-        // dbg!(ref_2);
-        // dbg!(ref_3);
-
-        // dbg!(ref_1_change);
-        // dbg!(ref_2_change);
-        // dbg!(ref_3_change);
-
-        if ref_2_change > rapid_criteria.1 && ref_3_change > rapid_criteria.3 {
-            Ok(RapidlyRising)
-        } else if ref_1_change > change_criteria.1 {
-            Ok(Rising)
-        } else if ref_2_change < -rapid_criteria.1 && ref_3_change < -rapid_criteria.3 {
-            Ok(RapidlyFalling)
-        } else if ref_1_change < -change_criteria.1 {
-            Ok(Falling)
+        criteria: &TrendConfig,
+        max_gap: chrono::Duration,
+    ) -> Result<TrendReport, ()> {
+        let (latest_time, latest) = db.last_key_value().ok_or(())?;
+        let latest_time = *latest_time;
+        let latest = get_field(latest).ok_or(())?;
+
+        let mut change_since = |minutes: i64| {
+            let target = latest_time - chrono::Duration::minutes(minutes);
+            let (t, v) = db_value_at(db, target, &mut get_field).ok_or(())?;
+
+            if t >= latest_time {
+                return Err(());
+            }
+
+            // a station that went quiet for a while can't tell us how it got here
+            let times: Vec<_> = db.range(t..=latest_time).map(|(t, _)| *t).collect();
+            if times.windows(2).any(|w| w[1] - w[0] > max_gap) {
+                return Err(());
+            }
+
+            Ok((latest - v, latest_time - t))
+        };
+
+        let (ref_1_change, ref_1_span) = change_since(criteria.change_window)?;
+        let (ref_2_change, _) = change_since(criteria.rapid_window)?;
+        let (ref_3_change, _) = change_since(criteria.rapid_confirm_window)?;
+
+        let trend = if ref_2_change > criteria.rapid && ref_3_change > criteria.rapid_confirm {
+            RapidlyRising
+        } else if ref_1_change > criteria.change {
+            Rising
+        } else if ref_2_change < -criteria.rapid && ref_3_change < -criteria.rapid_confirm {
+            RapidlyFalling
+        } else if ref_1_change < -criteria.change {
+            Falling
         } else {
-            Ok(Neutral)
-        }
+            Neutral
+        };
+
+        Ok(TrendReport {
+            trend,
+            change: Some((ref_1_change, ref_1_span)),
+        })
     }
 
-    fn from_db<'a, F: FnMut(&'a WxStructDeserialized) -> Option<f32>>(
-        db: &'a BTreeMap<DateTime<Utc>, WxStructDeserialized>,
+    fn from_db<'a, F: FnMut(&'a Entry) -> Option<f32>>(
+        db: &'a History,
         get_field: F,
-        change_criteria: (chrono::Duration, f32),
-        rapid_criteria: (chrono::Duration, f32, chrono::Duration, f32),
-    ) -> Trend {
-        match Trend::from_db_inner(db, get_field, change_criteria, rapid_criteria) {
+        criteria: &TrendConfig,
+        max_gap: chrono::Duration,
+    ) -> TrendReport {
+        match Trend::from_db_inner(db, get_field, criteria, max_gap) {
             Ok(tr) => tr,
-            Err(_) => UnknownChange,
+            Err(_) => TrendReport::unknown(),
         }
     }
 
@@ -242,8 +274,215 @@ impl fmt::Display for Trend {
 
 // FORMATTERS ------------------------------------------------------------------------------------------------------
 
-fn format_dewpoint(e: &WxEntryStruct) -> (WeatherData, WeatherData) {
-    let near_surface = e.layer(Layer::NearSurface);
+pub type History = BTreeMap<DateTime<Utc>, Entry>;
+
+// ENTRIES ------------------------------------------------------------------------------------------------------------
+
+// An entry with what WxEntryStruct has no room for. Derefs to the entry, so
+// it can be used anywhere one is.
+#[derive(Debug)]
+pub struct Entry {
+    pub wx: WxEntryStruct,
+    pub gust: Option<Speed>,
+    pub cloud_cover: Option<Fraction>,
+    pub uv_index: Option<f32>,
+    // a model's own apparent temperature, which accounts for the sun
+    pub apparent_temp: Option<Temperature>,
+}
+
+impl From<WxEntryStruct> for Entry {
+    fn from(wx: WxEntryStruct) -> Self {
+        Entry {
+            wx,
+            gust: None,
+            cloud_cover: None,
+            uv_index: None,
+            apparent_temp: None,
+        }
+    }
+}
+
+impl Deref for Entry {
+    type Target = WxEntryStruct;
+
+    fn deref(&self) -> &WxEntryStruct {
+        &self.wx
+    }
+}
+
+impl DerefMut for Entry {
+    fn deref_mut(&mut self) -> &mut WxEntryStruct {
+        &mut self.wx
+    }
+}
+
+fn station_layer(station: &StationConfig) -> Layer {
+    match station.layer() {
+        StationLayer::Indoor => Layer::Indoor,
+        StationLayer::NearSurface => Layer::NearSurface,
+    }
+}
+
+// everything that fell, in inches.
+fn precip_total(p: &Precip) -> f32 {
+    p.rain.value_in(Inch) + p.snow.value_in(Inch) + p.unknown.value_in(Inch)
+}
+
+fn c_to_f(c: f32) -> f32 {
+    c * 9. / 5. + 32.
+}
+
+fn temp_c(e: &Entry, layer: Layer) -> Option<f32> {
+    e.layer(layer)?.temperature.map(|x| x.value_in(Celsius))
+}
+
+fn dewpoint_c(e: &Entry, layer: Layer) -> Option<f32> {
+    e.layer(layer)?.dewpoint().map(|x| x.value_in(Celsius))
+}
+
+fn relative_humidity(e: &Entry, layer: Layer) -> Option<f32> {
+    e.layer(layer)?
+        .relative_humidity()
+        .map(|x| x.value_in(Percent))
+        .or_else(|| {
+            Some(derived::relative_humidity(
+                temp_c(e, layer)?,
+                dewpoint_c(e, layer)?,
+            ))
+        })
+}
+
+// heat index or wind chill when the source doesn't give a feels like
+// temperature, otherwise it feels like what it is.
+fn derived_apparent_temp(e: &Entry, layer: Layer) -> Option<f32> {
+    if layer != Layer::NearSurface {
+        return None;
+    }
+
+    let t = c_to_f(temp_c(e, layer)?);
+    let heat_index = relative_humidity(e, layer).and_then(|rh| derived::heat_index(t, rh));
+    let wind_chill = e
+        .layer(layer)?
+        .wind()
+        .and_then(|w| derived::wind_chill(t, w.speed.value_in(Mph)));
+
+    Some(heat_index.or(wind_chill).unwrap_or(t))
+}
+
+// feet, from the altimeter if there is one. Sea level pressure is close enough
+// for forecasts, a bare station pressure already is one.
+fn pressure_altitude(e: &Entry) -> Option<f32> {
+    let elevation = e.station.altitude.value_in(Feet);
+
+    match e.altimeter.or_else(|| e.best_slp()) {
+        Some(altimeter) => Some(derived::pressure_altitude(
+            elevation,
+            altimeter.value_in(Mbar),
+        )),
+        None => e
+            .layer(Layer::NearSurface)?
+            .pressure
+            .map(|p| derived::pressure_altitude(0., p.value_in(Mbar))),
+    }
+}
+
+// a parameter's value in the units it is displayed in.
+pub fn param_value(e: &Entry, param: &WxParams, layer: Layer) -> Option<f32> {
+    let surface = e.layer(Layer::NearSurface);
+
+    match param {
+        WxParams::Temperature => e.layer(layer)?.temperature.map(|x| x.value_in(Fahrenheit)),
+        WxParams::ApparentTemp => e
+            .apparent_temp
+            .filter(|_| layer == Layer::NearSurface)
+            .or_else(|| e.layer(layer)?.apparent_temp())
+            .map(|x| x.value_in(Fahrenheit))
+            .or_else(|| derived_apparent_temp(e, layer)),
+        WxParams::Dewpoint => e.layer(layer)?.dewpoint().map(|x| x.value_in(Fahrenheit)),
+        WxParams::RelativeHumidity => e
+            .layer(layer)?
+            .relative_humidity()
+            .map(|x| x.value_in(Percent)),
+        WxParams::Pressure => e.best_slp().map(|x| x.value_in(Mbar)),
+        WxParams::Wind => surface?.wind().map(|x| x.speed.value_in(Knots)),
+        WxParams::Visibility => surface?.visibility.map(|x| x.value_in(Mile)),
+        WxParams::Cape => e.cape.map(|x| x.value_in(Jkg)),
+        WxParams::Height500mb => e
+            .layer(Layer::MBAR(500))?
+            .height_msl()
+            .map(|x| x.value_in(Meter) / 10.),
+        WxParams::Wind250mb => e
+            .layer(Layer::MBAR(250))?
+            .wind()
+            .map(|x| x.speed.value_in(Knots)),
+        WxParams::Precip => e.precip.as_ref().map(precip_total),
+        WxParams::PrecipProbability => e.precip_probability.map(|x| x.value_in(Percent)),
+        WxParams::Snowfall => e.precip.as_ref().map(|x| x.snow.value_in(Inch)),
+        WxParams::PrecipToday => e.precip_today.as_ref().map(precip_total),
+        WxParams::Gust => e.gust.map(|x| x.value_in(Knots)),
+        WxParams::CloudCover => e.cloud_cover.map(|x| x.value_in(Percent)),
+        WxParams::UvIndex => e.uv_index,
+        WxParams::WetBulb => Some(c_to_f(derived::wet_bulb(
+            temp_c(e, layer)?,
+            relative_humidity(e, layer)?,
+        ))),
+        WxParams::Wbgt => Some(c_to_f(derived::wbgt(
+            temp_c(e, layer)?,
+            dewpoint_c(e, layer)?,
+        ))),
+        WxParams::Humidex => Some(derived::humidex(temp_c(e, layer)?, dewpoint_c(e, layer)?)),
+        WxParams::FrostPoint => Some(c_to_f(derived::frost_point(dewpoint_c(e, layer)?))),
+        WxParams::PressureAltitude => pressure_altitude(e),
+        WxParams::DensityAltitude => Some(derived::density_altitude(
+            pressure_altitude(e)?,
+            temp_c(e, Layer::NearSurface)?,
+            dewpoint_c(e, Layer::NearSurface),
+        )),
+        _ => None,
+    }
+}
+
+// what the formatters need to know beyond the entry itself.
+struct LineContext<'a> {
+    station: &'a StationConfig,
+    db: &'a History,
+    config: &'a Config,
+}
+
+impl LineContext<'_> {
+    fn layer(&self) -> Layer {
+        station_layer(self.station)
+    }
+
+    fn trend(&self, param: WxParams, unit: &str) -> String {
+        let Some(criteria) = self.station.trend(&param, &self.config.weather.trends) else {
+            return String::new();
+        };
+
+        let layer = self.layer();
+
+        // the raw change behind each arrow is only shown when extra verbose
+        let max_gap = self.station.max_age(self.config.weather.max_age);
+
+        Trend::from_db(
+            self.db,
+            |e| param_value(e, &param, layer),
+            &criteria,
+            max_gap,
+        )
+        .text(unit, self.config.verbosity >= 2)
+    }
+
+    // the user's color scale, if they have one for this parameter.
+    fn style(&self, param: WxParams, value: f32) -> Option<String> {
+        self.station
+            .color_scale(&param, &self.config.weather.colors)?
+            .style(value)
+    }
+}
+
+fn format_dewpoint(e: &WxEntryStruct, ctx: &LineContext) -> (WeatherData, WeatherData) {
+    let near_surface = e.layer(ctx.layer());
     let dewpoint = near_surface.and_then(|x| x.dewpoint());
 
     if dewpoint.is_none() {
@@ -252,7 +491,9 @@ fn format_dewpoint(e: &WxEntryStruct) -> (WeatherData, WeatherData) {
 
     let a = dewpoint.unwrap().value_in(Fahrenheit);
 
-    let dew_style: String = if a > 70. {
+    let dew_style: String = if let Some(style) = ctx.style(WxParams::Dewpoint, a) {
+        style
+    } else if a > 70. {
         Style::string(&[PurpleBg, Black, Bold])
     } else if a > 60. {
         Style::string(&[BlueBg, Black, Bold])
@@ -264,7 +505,7 @@ fn format_dewpoint(e: &WxEntryStruct) -> (WeatherData, WeatherData) {
         Style::string(&[Bold])
     };
 
-    let dew_text: String = format!("{a:.0}F");
+    let dew_text: String = format!("{a:.0}F{}", ctx.trend(WxParams::Dewpoint, "F"));
 
     let rh_text: String;
     let rh_style: String;
@@ -273,7 +514,9 @@ fn format_dewpoint(e: &WxEntryStruct) -> (WeatherData, WeatherData) {
     match rh {
         Some(a) => {
             let a = a.value_in(Percent);
-            rh_style = if a > 95. {
+            rh_style = if let Some(style) = ctx.style(WxParams::RelativeHumidity, a) {
+                style
+            } else if a > 95. {
                 Style::string(&[PurpleBg, Black, Bold])
             } else if a > 90. {
                 Style::string(&[BlueBg, Black, Bold])
@@ -285,7 +528,7 @@ fn format_dewpoint(e: &WxEntryStruct) -> (WeatherData, WeatherData) {
                 Style::string(&[YellowBg, Black, Bold])
             };
 
-            rh_text = format!("{a:.0}%");
+            rh_text = format!("{a:.0}%{}", ctx.trend(WxParams::RelativeHumidity, "%"));
         }
         None => {
             rh_text = String::from("N/A");
@@ -307,7 +550,7 @@ fn format_dewpoint(e: &WxEntryStruct) -> (WeatherData, WeatherData) {
     )
 }
 
-fn format_wind(e: &WxEntryStruct) -> WeatherData {
+fn format_wind(e: &Entry, ctx: &LineContext) -> WeatherData {
     let near_surface = e.layers.get(&Layer::NearSurface);
     let wind = near_surface.and_then(|x| x.wind());
 
@@ -318,28 +561,33 @@ fn format_wind(e: &WxEntryStruct) -> WeatherData {
     let a = wind.unwrap();
     let speed = a.speed.value_in(Knots);
 
-    let style: String = if speed > 45. {
-        Style::string(&[YellowBg, Black, Bold])
-    } else if speed > 32. {
-        Style::string(&[RedBg, Black, Bold])
-    } else if speed > 20. {
-        Style::string(&[PurpleBg, Black, Bold])
-    } else if speed > 12. {
-        Style::string(&[BlueBg, Black, Bold])
-    } else {
-        Style::string(&[Bold])
-    };
+    let style: String = ctx
+        .style(WxParams::Wind, speed)
+        .unwrap_or_else(|| wind_speed_style(speed));
+
+    // only worth mentioning when they're stronger than the wind
+    let gust = param_value(e, &WxParams::Gust, Layer::NearSurface)
+        .filter(|g| *g > speed)
+        .map(|g| format!("G{g:.0}"))
+        .unwrap_or_default();
 
-    let text: String = if speed > 0. {
+    let mut text: String = if speed > 0. {
         if let Some(dir) = a.direction {
-            format!("{:03}({})@{:2.0}kts", dir.degrees(), dir.cardinal(), speed)
+            format!(
+                "{:03}({})@{:2.0}{gust}kts",
+                dir.degrees(),
+                dir.cardinal(),
+                speed
+            )
         } else {
-            format!("{speed:2.0}kts")
+            format!("{speed:2.0}{gust}kts")
         }
     } else {
         String::from("Calm")
     };
 
+    text.push_str(&ctx.trend(WxParams::Wind, "kts"));
+
     WeatherData {
         title: "Wind".into(),
         text,
@@ -386,9 +634,9 @@ fn format_cloud(s: &WxEntryStruct) -> WeatherData {
     }
 }
 
-fn format_visibility(e: &WxEntryStruct) -> WeatherData {
+fn format_visibility(e: &WxEntryStruct, ctx: &LineContext) -> WeatherData {
     let text: String;
-    let style: String;
+    let mut style: String;
 
     let near_surface = e.layers.get(&Layer::NearSurface);
     let visibility = near_surface.and_then(|x| x.visibility);
@@ -407,12 +655,16 @@ fn format_visibility(e: &WxEntryStruct) -> WeatherData {
                 style = Style::string(&[Bold]);
                 text = format!("{v:.0}mi");
             }
+
+            if let Some(s) = ctx.style(WxParams::Visibility, v) {
+                style = s;
+            }
         }
     }
 
     WeatherData {
         title: "Vis".into(),
-        text,
+        text: text + &ctx.trend(WxParams::Visibility, "mi"),
         style,
     }
 }
@@ -480,55 +732,14 @@ fn outdoor_temp_style(temp: Temperature) -> String {
     }
 }
 
-fn format_temp(
-    e: &WxEntryStruct,
-    indoor: bool,
-    db: &BTreeMap<DateTime<Utc>, WxStructDeserialized>,
-) -> WeatherData {
-    let temp = if indoor {
-        e.layers.get(&Layer::Indoor).map(|x| x.temperature)
-    } else {
-        e.layers.get(&Layer::NearSurface).map(|x| x.temperature)
-    };
+fn format_temp(e: &WxEntryStruct, ctx: &LineContext) -> WeatherData {
+    let temp = e.layers.get(&ctx.layer()).and_then(|x| x.temperature);
 
-    let temp_change = if indoor {
-        Trend::from_db(
-            db,
-            |data| {
-                data.layers
-                    .get(&Layer::Indoor)
-                    .and_then(|x| x.temperature)
-                    .map(|x| x.value_in(Fahrenheit))
-            },
-            (chrono::Duration::hours(2), 2.),
-            (
-                chrono::Duration::hours(1),
-                2.,
-                chrono::Duration::hours(1),
-                2.,
-            ),
-        )
-    } else {
-        Trend::from_db(
-            db,
-            |data| {
-                data.layers
-                    .get(&Layer::NearSurface)
-                    .and_then(|x| x.temperature)
-                    .map(|x| x.value_in(Fahrenheit))
-            },
-            (chrono::Duration::hours(2), 4.),
-            (
-                chrono::Duration::minutes(15),
-                2.,
-                chrono::Duration::hours(1),
-                4.,
-            ),
-        )
-    };
-
-    if let Some(temp) = temp.flatten() {
-        let style = if indoor {
+    if let Some(temp) = temp {
+        let style = if let Some(style) = ctx.style(WxParams::Temperature, temp.value_in(Fahrenheit))
+        {
+            style
+        } else if ctx.station.indoor {
             indoor_temp_style(temp)
         } else {
             outdoor_temp_style(temp)
@@ -537,21 +748,27 @@ fn format_temp(
         WeatherData {
             title: "Temp".into(),
             style,
-            text: format!("{:.0}F{temp_change}", temp.value_in(Fahrenheit)),
+            text: format!(
+                "{:.0}F{}",
+                temp.value_in(Fahrenheit),
+                ctx.trend(WxParams::Temperature, "F")
+            ),
         }
     } else {
         WeatherData::none()
     }
 }
 
-fn format_apparent_temp(e: &WxEntryStruct) -> WeatherData {
-    let apparent_temp = e
-        .layers
-        .get(&Layer::NearSurface)
-        .and_then(|x| x.apparent_temp());
+fn format_apparent_temp(e: &Entry, ctx: &LineContext) -> WeatherData {
+    let apparent_temp = param_value(e, &WxParams::ApparentTemp, ctx.layer());
 
-    if let Some(a) = apparent_temp {
-        let style = outdoor_temp_style(a);
+    if let Some(value) = apparent_temp {
+        let a = Temperature::new(value, Fahrenheit);
+        // falls back to the temperature scale, like the built in colors do
+        let style = ctx
+            .style(WxParams::ApparentTemp, value)
+            .or_else(|| ctx.style(WxParams::Temperature, value))
+            .unwrap_or_else(|| outdoor_temp_style(a));
         WeatherData {
             title: "Feels".into(),
             text: format!("{:.0}F", a.value_in(Fahrenheit)),
@@ -575,32 +792,17 @@ fn mslp_style(pres: Pressure) -> String {
     }
 }
 
-fn format_pressure(
-    e: &WxEntryStruct,
-    db: &BTreeMap<DateTime<Utc>, WxStructDeserialized>,
-) -> WeatherData {
-    // dbg!(&e);
-
+fn format_pressure(e: &WxEntryStruct, ctx: &LineContext) -> WeatherData {
     let slp = e.best_slp();
-    // dbg!(slp);
 
     if let Some(pressure) = slp {
-        let style = mslp_style(pressure);
-        let pres_change = Trend::from_db(
-            db,
-            |data| data.best_slp().map(|x| x.value_in(Mbar)),
-            (chrono::Duration::hours(6), 3.),
-            (
-                chrono::Duration::minutes(15),
-                1.,
-                chrono::Duration::hours(3),
-                2.,
-            ),
-        );
+        let style = ctx
+            .style(WxParams::Pressure, pressure.value_in(Mbar))
+            .unwrap_or_else(|| mslp_style(pressure));
 
         WeatherData {
             title: "Pres".into(),
-            text: format!("{pressure:.1}{pres_change}"),
+            text: format!("{pressure:.1}{}", ctx.trend(WxParams::Pressure, "mb")),
             style,
         }
     } else {
@@ -622,15 +824,17 @@ fn style_500mb_height(h: f32) -> String {
     }
 }
 
-fn format_500mb_height(e: &WxEntryStruct) -> WeatherData {
+fn format_500mb_height(e: &WxEntryStruct, ctx: &LineContext) -> WeatherData {
     if let Some(l) = e.layers.get(&Layer::MBAR(500))
         && let Some(h) = l.height_msl()
     {
         let dam = h.value_in(Meter) / 10.; // get height in decameters
         return WeatherData {
             title: "500mb Hght".into(),
-            text: format!("{:.0}0m", dam),
-            style: style_500mb_height(dam),
+            text: format!("{:.0}0m{}", dam, ctx.trend(WxParams::Height500mb, "dam")),
+            style: ctx
+                .style(WxParams::Height500mb, dam)
+                .unwrap_or_else(|| style_500mb_height(dam)),
         };
     }
 
@@ -653,24 +857,28 @@ fn style_250mb_wind(a: Wind) -> String {
     }
 }
 
-fn format_250mb_wind(e: &WxEntryStruct) -> WeatherData {
+fn format_250mb_wind(e: &WxEntryStruct, ctx: &LineContext) -> WeatherData {
     if let Some(l) = e.layers.get(&Layer::MBAR(250))
         && let Some(wind) = l.wind()
     {
         return WeatherData {
             title: "250mb".into(),
             text: format!("{:2.0}kts", wind.speed.value_in(Knots)),
-            style: style_250mb_wind(wind),
+            style: ctx
+                .style(WxParams::Wind250mb, wind.speed.value_in(Knots))
+                .unwrap_or_else(|| style_250mb_wind(wind)),
         };
     }
 
     WeatherData::none()
 }
 
-fn format_cape(e: &WxEntryStruct) -> WeatherData {
+fn format_cape(e: &WxEntryStruct, ctx: &LineContext) -> WeatherData {
     if let Some(cape) = e.cape {
         let cape = cape.value_in(Jkg);
-        let style = if cape < 200. {
+        let style = if let Some(style) = ctx.style(WxParams::Cape, cape) {
+            style
+        } else if cape < 200. {
             Style::string(&[])
         } else if cape < 1000. {
             Style::string(&[Bold])
@@ -690,7 +898,7 @@ fn format_cape(e: &WxEntryStruct) -> WeatherData {
 
         WeatherData {
             title: "CAPE".into(),
-            text: format!("{cape:.0} J/kg"),
+            text: format!("{cape:.0} J/kg{}", ctx.trend(WxParams::Cape, "J/kg")),
             style,
         }
     } else {
@@ -698,7 +906,290 @@ fn format_cape(e: &WxEntryStruct) -> WeatherData {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+// hourly amounts, so a tenth of an inch is already a decent rain.
+fn format_precip(e: &Entry, ctx: &LineContext) -> WeatherData {
+    let Some(precip) = param_value(e, &WxParams::Precip, Layer::NearSurface) else {
+        return WeatherData::none();
+    };
+
+    let style = if let Some(style) = ctx.style(WxParams::Precip, precip) {
+        style
+    } else if precip <= 0. {
+        Style::string(&[])
+    } else if precip < 0.1 {
+        Style::string(&[Blue, Bold])
+    } else if precip < 0.3 {
+        Style::string(&[BlueBg, Black, Bold])
+    } else {
+        Style::string(&[PurpleBg, White, Bold])
+    };
+
+    WeatherData {
+        title: "Precip".into(),
+        text: format!("{precip:.2}in"),
+        style,
+    }
+}
+
+fn format_precip_probability(e: &Entry, ctx: &LineContext) -> WeatherData {
+    let Some(pop) = param_value(e, &WxParams::PrecipProbability, Layer::NearSurface) else {
+        return WeatherData::none();
+    };
+
+    let style = if let Some(style) = ctx.style(WxParams::PrecipProbability, pop) {
+        style
+    } else if pop < 20. {
+        Style::string(&[])
+    } else if pop < 50. {
+        Style::string(&[Bold])
+    } else if pop < 80. {
+        Style::string(&[Blue, Bold])
+    } else {
+        Style::string(&[BlueBg, Black, Bold])
+    };
+
+    WeatherData {
+        title: "PoP".into(),
+        text: format!("{pop:.0}%"),
+        style,
+    }
+}
+
+// only shown when it's snowing, most of the year it would always be zero.
+fn format_snowfall(e: &Entry, ctx: &LineContext) -> WeatherData {
+    let Some(snow) = param_value(e, &WxParams::Snowfall, Layer::NearSurface) else {
+        return WeatherData::none();
+    };
+
+    if snow <= 0. {
+        return WeatherData::none();
+    }
+
+    let style = if let Some(style) = ctx.style(WxParams::Snowfall, snow) {
+        style
+    } else if snow < 0.5 {
+        Style::string(&[Bold])
+    } else if snow < 1. {
+        Style::string(&[WhiteBg, Blue, Bold])
+    } else {
+        Style::string(&[BlueBg, White, Bold])
+    };
+
+    WeatherData {
+        title: "Snow".into(),
+        text: format!("{snow:.1}in"),
+        style,
+    }
+}
+
+fn format_precip_today(e: &Entry, ctx: &LineContext) -> WeatherData {
+    let Some(precip) = param_value(e, &WxParams::PrecipToday, Layer::NearSurface) else {
+        return WeatherData::none();
+    };
+
+    let style = if let Some(style) = ctx.style(WxParams::PrecipToday, precip) {
+        style
+    } else if precip <= 0. {
+        Style::string(&[])
+    } else if precip < 0.5 {
+        Style::string(&[Blue, Bold])
+    } else if precip < 1. {
+        Style::string(&[BlueBg, Black, Bold])
+    } else {
+        Style::string(&[PurpleBg, White, Bold])
+    };
+
+    WeatherData {
+        title: "Today".into(),
+        text: format!("{precip:.2}in"),
+        style,
+    }
+}
+
+fn wind_speed_style(speed: f32) -> String {
+    if speed > 45. {
+        Style::string(&[YellowBg, Black, Bold])
+    } else if speed > 32. {
+        Style::string(&[RedBg, Black, Bold])
+    } else if speed > 20. {
+        Style::string(&[PurpleBg, Black, Bold])
+    } else if speed > 12. {
+        Style::string(&[BlueBg, Black, Bold])
+    } else {
+        Style::string(&[Bold])
+    }
+}
+
+fn format_gust(e: &Entry, ctx: &LineContext) -> WeatherData {
+    let Some(gust) = param_value(e, &WxParams::Gust, Layer::NearSurface) else {
+        return WeatherData::none();
+    };
+
+    WeatherData {
+        title: "Gust".into(),
+        text: format!("{gust:.0}kts{}", ctx.trend(WxParams::Gust, "kts")),
+        style: ctx
+            .style(WxParams::Gust, gust)
+            .unwrap_or_else(|| wind_speed_style(gust)),
+    }
+}
+
+fn format_cloud_cover(e: &Entry, ctx: &LineContext) -> WeatherData {
+    let Some(cover) = param_value(e, &WxParams::CloudCover, Layer::NearSurface) else {
+        return WeatherData::none();
+    };
+
+    let style = if let Some(style) = ctx.style(WxParams::CloudCover, cover) {
+        style
+    } else if cover > 85. {
+        Style::string(&[WhiteBg, Black, Bold])
+    } else {
+        Style::string(&[Bold])
+    };
+
+    WeatherData {
+        title: "Cover".into(),
+        text: format!("{cover:.0}%"),
+        style,
+    }
+}
+
+// the WHO's exposure categories.
+fn format_uv_index(e: &Entry, ctx: &LineContext) -> WeatherData {
+    let Some(uv) = param_value(e, &WxParams::UvIndex, Layer::NearSurface) else {
+        return WeatherData::none();
+    };
+
+    let (category, style) = match uv.round() {
+        ..3. => ("Low", Style::string(&[Green, Bold])),
+        ..6. => ("Moderate", Style::string(&[YellowBg, Black, Bold])),
+        ..8. => ("High", Style::string(&[RedBg, Black, Bold])),
+        ..11. => ("Very High", Style::string(&[RedBg, White, Bold])),
+        _ => ("Extreme", Style::string(&[PurpleBg, White, Bold])),
+    };
+
+    WeatherData {
+        title: "UV".into(),
+        text: format!("{uv:.0} {category}"),
+        style: ctx.style(WxParams::UvIndex, uv).unwrap_or(style),
+    }
+}
+
+// wet bulbs near 95F are beyond what people can shed heat in.
+fn format_wet_bulb(e: &Entry, ctx: &LineContext) -> WeatherData {
+    let Some(tw) = param_value(e, &WxParams::WetBulb, ctx.layer()) else {
+        return WeatherData::none();
+    };
+
+    let style = if tw > 86. {
+        Style::string(&[RedBg, White, Bold])
+    } else if tw > 77. {
+        Style::string(&[YellowBg, Black, Bold])
+    } else {
+        Style::string(&[Bold])
+    };
+
+    WeatherData {
+        title: "Tw".into(),
+        text: format!("{tw:.0}F"),
+        style: ctx.style(WxParams::WetBulb, tw).unwrap_or(style),
+    }
+}
+
+// the flag colors used for outdoor activity limits.
+fn format_wbgt(e: &Entry, ctx: &LineContext) -> WeatherData {
+    let Some(wbgt) = param_value(e, &WxParams::Wbgt, ctx.layer()) else {
+        return WeatherData::none();
+    };
+
+    let style = match wbgt {
+        ..80. => Style::string(&[Bold]),
+        ..85. => Style::string(&[GreenBg, Black, Bold]),
+        ..88. => Style::string(&[YellowBg, Black, Bold]),
+        ..90. => Style::string(&[RedBg, White, Bold]),
+        _ => Style::string(&[BlackBg, White, Bold]),
+    };
+
+    WeatherData {
+        title: "WBGT".into(),
+        text: format!("{wbgt:.0}F"),
+        style: ctx.style(WxParams::Wbgt, wbgt).unwrap_or(style),
+    }
+}
+
+// Environment Canada's comfort ranges.
+fn format_humidex(e: &Entry, ctx: &LineContext) -> WeatherData {
+    let Some(humidex) = param_value(e, &WxParams::Humidex, ctx.layer()) else {
+        return WeatherData::none();
+    };
+
+    let style = match humidex {
+        ..30. => Style::string(&[Bold]),
+        ..40. => Style::string(&[YellowBg, Black, Bold]),
+        ..46. => Style::string(&[RedBg, White, Bold]),
+        _ => Style::string(&[PurpleBg, White, Bold]),
+    };
+
+    WeatherData {
+        title: "Humidex".into(),
+        text: format!("{humidex:.0}"),
+        style: ctx.style(WxParams::Humidex, humidex).unwrap_or(style),
+    }
+}
+
+// only interesting when it's cold enough for frost.
+fn format_frost_point(e: &Entry, ctx: &LineContext) -> WeatherData {
+    let Some(frost) = param_value(e, &WxParams::FrostPoint, ctx.layer()) else {
+        return WeatherData::none();
+    };
+
+    if frost > 32. {
+        return WeatherData::none();
+    }
+
+    WeatherData {
+        title: "Frost".into(),
+        text: format!("{frost:.0}F"),
+        style: ctx
+            .style(WxParams::FrostPoint, frost)
+            .unwrap_or_else(|| Style::string(&[Cyan, Bold])),
+    }
+}
+
+fn format_pressure_altitude(e: &Entry, ctx: &LineContext) -> WeatherData {
+    let Some(pa) = param_value(e, &WxParams::PressureAltitude, ctx.layer()) else {
+        return WeatherData::none();
+    };
+
+    WeatherData {
+        title: "PA".into(),
+        text: format!("{pa:.0}ft"),
+        style: ctx
+            .style(WxParams::PressureAltitude, pa)
+            .unwrap_or_else(|| Style::string(&[Bold])),
+    }
+}
+
+// colored by how much higher the air acts than the field actually is.
+fn format_density_altitude(e: &Entry, ctx: &LineContext) -> WeatherData {
+    let Some(da) = param_value(e, &WxParams::DensityAltitude, ctx.layer()) else {
+        return WeatherData::none();
+    };
+
+    let style = match da - e.station.altitude.value_in(Feet) {
+        ..2000. => Style::string(&[Bold]),
+        ..4000. => Style::string(&[YellowBg, Black, Bold]),
+        _ => Style::string(&[RedBg, White, Bold]),
+    };
+
+    WeatherData {
+        title: "DA".into(),
+        text: format!("{da:.0}ft"),
+        style: ctx.style(WxParams::DensityAltitude, da).unwrap_or(style),
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 #[allow(clippy::upper_case_acronyms)]
 enum FlightRules {
     VFR,
@@ -730,48 +1221,48 @@ impl FlightRules {
     }
 }
 
-fn format_flight_rules(e: &WxEntryStruct) -> WeatherData {
+// the lowest layer that counts as a ceiling for the flight rules.
+fn ceiling(e: &WxEntryStruct) -> Option<&CloudLayer> {
     use CloudLayerCoverage::*;
-    use FlightRules::*;
 
-    let mut ceiling_height = u32::MAX;
+    match &e.skycover {
+        Some(SkyCoverage::Cloudy(v)) => v
+            .iter()
+            .filter(|x| matches!(x.coverage, Scattered | Broken | Overcast))
+            .min_by_key(|x| x.height),
+        _ => None,
+    }
+}
 
-    let near_surface = e.layers.get(&Layer::NearSurface);
-    let visibility = near_surface.and_then(|x| x.visibility);
+fn flight_rules(e: &WxEntryStruct) -> Option<FlightRules> {
+    use FlightRules::*;
 
-    if let (Some(cover), Some(vis)) = (&e.skycover, visibility) {
-        if let SkyCoverage::Cloudy(v) = cover {
-            for layer in v {
-                match layer.coverage {
-                    Scattered | Broken | Overcast => {
-                        if layer.height < ceiling_height {
-                            ceiling_height = layer.height;
-                        }
-                    }
-                    Few => {}
-                }
-            }
-        } // otherwise we have clear skys
-
-        let vis = vis.value_in(Mile);
-
-        let fr: FlightRules = match ceiling_height {
-            0..=499 => LIFR,
-            _ if vis < 1.0 => LIFR,
-            500..=999 => IFR,
-            _ if vis < 3.0 => IFR,
-            1000..=2999 => MVFR,
-            _ if vis < 5.0 => MVFR,
-            _ => VFR,
-        };
+    let near_surface = e.layers.get(&Layer::NearSurface);
+    let vis = near_surface.and_then(|x| x.visibility)?.value_in(Mile);
+
+    // without a sky cover there's no telling clear from missing
+    e.skycover.as_ref()?;
+    let ceiling_height = ceiling(e).map_or(u32::MAX, |x| x.height);
+
+    Some(match ceiling_height {
+        0..=499 => LIFR,
+        _ if vis < 1.0 => LIFR,
+        500..=999 => IFR,
+        _ if vis < 3.0 => IFR,
+        1000..=2999 => MVFR,
+        _ if vis < 5.0 => MVFR,
+        _ => VFR,
+    })
+}
 
-        WeatherData {
+fn format_flight_rules(e: &WxEntryStruct) -> WeatherData {
+    match flight_rules(e) {
+        Some(fr) => WeatherData {
             title: "".into(),
             text: fr.to_string(),
             style: fr.style(),
-        }
-    } else {
-        WeatherData::none()
+        },
+        None => WeatherData::none(),
     }
 }
 
@@ -816,43 +1307,290 @@ fn format_comfort_worst(e: &WxEntryStruct) -> WeatherData {
     }
 }
 
+// SPARKLINES ---------------------------------------------------------------------------------------------------------
+
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// title, unit and decimals a parameter's history is shown with.
+fn history_label(param: &WxParams) -> Option<(&'static str, &'static str, usize)> {
+    match param {
+        WxParams::Temperature => Some(("Temp", "F", 0)),
+        WxParams::ApparentTemp => Some(("Feels", "F", 0)),
+        WxParams::Dewpoint => Some(("Dew", "F", 0)),
+        WxParams::RelativeHumidity => Some(("RH", "%", 0)),
+        WxParams::Pressure => Some(("Pres", "mb", 1)),
+        WxParams::Wind => Some(("Wind", "kts", 0)),
+        WxParams::Visibility => Some(("Vis", "mi", 1)),
+        WxParams::Cape => Some(("CAPE", "J/kg", 0)),
+        WxParams::Height500mb => Some(("500mb", "dam", 0)),
+        WxParams::Wind250mb => Some(("250mb", "kts", 0)),
+        WxParams::Precip => Some(("Precip", "in", 2)),
+        WxParams::PrecipProbability => Some(("PoP", "%", 0)),
+        WxParams::Snowfall => Some(("Snow", "in", 1)),
+        WxParams::PrecipToday => Some(("Today", "in", 2)),
+        WxParams::Gust => Some(("Gust", "kts", 0)),
+        WxParams::CloudCover => Some(("Cover", "%", 0)),
+        WxParams::UvIndex => Some(("UV", "", 0)),
+        WxParams::WetBulb => Some(("Tw", "F", 0)),
+        WxParams::Wbgt => Some(("WBGT", "F", 0)),
+        WxParams::Humidex => Some(("Humidex", "", 0)),
+        WxParams::FrostPoint => Some(("Frost", "F", 0)),
+        WxParams::PressureAltitude => Some(("PA", "ft", 0)),
+        WxParams::DensityAltitude => Some(("DA", "ft", 0)),
+        _ => None,
+    }
+}
+
+fn sparkline(values: &[Option<f32>]) -> Option<(String, f32, f32)> {
+    let present = values.iter().flatten();
+    let min = present.clone().copied().reduce(f32::min)?;
+    let max = present.copied().reduce(f32::max)?;
+
+    let line = values
+        .iter()
+        .map(|v| match v {
+            None => ' ',
+            Some(_) if max == min => SPARK_LEVELS[SPARK_LEVELS.len() / 2],
+            Some(v) => {
+                let frac = (v - min) / (max - min);
+                SPARK_LEVELS[(frac * (SPARK_LEVELS.len() - 1) as f32).round() as usize]
+            }
+        })
+        .collect();
+
+    Some((line, min, max))
+}
+
+// one line per parameter, one character per hour, oldest first.
+pub fn sparklines(
+    parameters: &[WxParams],
+    station: &StationConfig,
+    db: &History,
+    config: &Config,
+) -> String {
+    let Some(latest) = db.last_key_value().map(|(t, _)| *t) else {
+        return String::new();
+    };
+
+    let hours = config.weather.sparkline_hours as i64;
+    let layer = station_layer(station);
+
+    let mut s = String::new();
+
+    for p in parameters.iter().filter(|p| station.shows(p)) {
+        let Some((title, unit, decimals)) = history_label(p) else {
+            continue;
+        };
+
+        let mut get_field = |e| param_value(e, p, layer);
+
+        let values: Vec<Option<f32>> = (0..hours)
+            .rev()
+            .map(|h| {
+                db_value_at(db, latest - chrono::Duration::hours(h), &mut get_field).map(|(_, v)| v)
+            })
+            .collect();
+
+        if let Some((line, min, max)) = sparkline(&values) {
+            s.push_str(&format!(
+                "  {title:<6}{Bold}{line}{Reset} {hours}h min {min:.decimals$}{unit}, max {max:.decimals$}{unit}\n"
+            ));
+        }
+    }
+
+    s
+}
+
+// TODAY SO FAR -------------------------------------------------------------------------------------------------------
+
+fn extreme<F: Fn(&Entry) -> Option<f32>>(
+    today: &[(&DateTime<Utc>, &Entry)],
+    get_field: F,
+    highest: bool,
+) -> Option<(f32, DateTime<Utc>)> {
+    today
+        .iter()
+        .filter_map(|(t, e)| get_field(e).map(|v| (v, **t)))
+        .reduce(|a, b| {
+            if (highest && b.0 > a.0) || (!highest && b.0 < a.0) {
+                b
+            } else {
+                a
+            }
+        })
+}
+
+fn at_time(t: DateTime<Utc>) -> String {
+    let local: DateTime<Local> = t.into();
+    local.format("%l:%M %p").to_string().trim().to_string()
+}
+
+// high/low, peak wind, precipitation and pressure range since local midnight.
+// A SPECI's precip is everything since the last routine report, which the next
+// routine report counts again. So only routine reports are added up, along
+// with a SPECI after the last of them.
+fn summed_precip(entries: &[(&DateTime<Utc>, &Entry)]) -> Option<f32> {
+    let mut total = None;
+    let mut since_routine = None;
+
+    for (_, e) in entries {
+        let Some(p) = e.precip.as_ref().map(precip_total) else {
+            continue;
+        };
+
+        let speci = e
+            .raw_metar
+            .as_deref()
+            .is_some_and(|m| m.trim_start().starts_with("SPECI"));
+
+        if speci {
+            since_routine = Some(p);
+        } else {
+            total = Some(total.unwrap_or(0.) + p);
+            since_routine = None;
+        }
+    }
+
+    match (total, since_routine) {
+        (None, None) => None,
+        (total, since) => Some(total.unwrap_or(0.) + since.unwrap_or(0.)),
+    }
+}
+
+// "today" is the station's day, in its configured timezone or the one wxer
+// reports. Other sources are read in local time, so that's their day.
+pub fn today_so_far(db: &History, station: &StationConfig, tz: Option<Tz>) -> String {
+    let tz = match tz {
+        Some(tz) => Some(tz),
+        None if station.kind == SourceKind::Wxer => {
+            db.last_key_value().map(|(_, e)| e.station.time_zone)
+        }
+        None => None,
+    };
+    let date = |t: &DateTime<Utc>| match tz {
+        Some(tz) => t.with_timezone(&tz).date_naive(),
+        None => t.with_timezone(&Local).date_naive(),
+    };
+
+    let today_date = date(&Utc::now());
+
+    let today: Vec<_> = db.iter().filter(|(t, _)| date(t) == today_date).collect();
+
+    let layer = station_layer(station);
+    let value = |p: WxParams| move |e: &Entry| param_value(e, &p, layer);
+
+    let mut parts = vec![];
+
+    if let Some((hi, t)) = extreme(&today, value(WxParams::Temperature), true) {
+        parts.push(format!("Hi {hi:.0}F @ {}", at_time(t)));
+    }
+    if let Some((lo, t)) = extreme(&today, value(WxParams::Temperature), false) {
+        parts.push(format!("Lo {lo:.0}F @ {}", at_time(t)));
+    }
+    // sustained wind if the source doesn't report gusts
+    if let Some((peak, t)) = extreme(&today, value(WxParams::Gust), true) {
+        parts.push(format!("Peak gust {peak:.0}kts @ {}", at_time(t)));
+    } else if let Some((peak, t)) = extreme(&today, value(WxParams::Wind), true) {
+        parts.push(format!("Peak wind {peak:.0}kts @ {}", at_time(t)));
+    }
+
+    // the running total if the source keeps one, otherwise add it up
+    let precip = match today.last().and_then(|(_, e)| e.precip_today.as_ref()) {
+        Some(p) => Some(precip_total(p)),
+        None => summed_precip(&today),
+    };
+    if let Some(precip) = precip {
+        parts.push(format!("Precip {precip:.2}in"));
+    }
+
+    if let (Some((lo, _)), Some((hi, _))) = (
+        extreme(&today, value(WxParams::Pressure), false),
+        extreme(&today, value(WxParams::Pressure), true),
+    ) {
+        parts.push(format!("Pres {lo:.1}-{hi:.1}mb"));
+    }
+
+    if parts.is_empty() {
+        return String::new();
+    }
+
+    format!("  {Bold}Today:{Reset} {}\n", parts.join(", "))
+}
+
 const COLUMN_WIDTH: usize = 80;
 
-use crate::config::WxParams;
+use crate::config::{Config, SourceKind, StationConfig, StationLayer, TrendConfig, WxParams};
+
+// every parameter's formatted value, in order. Ones that aren't shown for the
+// station, or don't apply, are left empty.
+fn format_params(
+    e: &Entry,
+    parameters: &[WxParams],
+    ctx: &LineContext,
+) -> Vec<(WxParams, WeatherData)> {
+    let (dewpoint, rh) = format_dewpoint(e, ctx);
+
+    parameters
+        .iter()
+        .map(|p| {
+            let data = match p {
+                _ if !ctx.station.shows(p) => WeatherData::none(),
+                WxParams::ApparentTemp => format_apparent_temp(e, ctx),
+                WxParams::Cape => format_cape(e, ctx),
+                WxParams::Cloud => format_cloud(e),
+                WxParams::Dewpoint => dewpoint.clone(),
+                WxParams::FlightRules => format_flight_rules(e),
+                WxParams::Height500mb => format_500mb_height(e, ctx),
+                WxParams::Metar => WeatherData::none(), // METARs are dealt with separately at the end
+                WxParams::Pressure => format_pressure(e, ctx),
+                WxParams::RelativeHumidity => rh.clone(),
+                WxParams::Temperature => format_temp(e, ctx),
+                WxParams::Visibility => format_visibility(e, ctx),
+                WxParams::Wind => format_wind(e, ctx),
+                WxParams::Wind250mb => format_250mb_wind(e, ctx),
+                WxParams::WxCode => format_wx(e.wx_codes.as_deref(), ctx),
+                WxParams::Precip => format_precip(e, ctx),
+                WxParams::PrecipProbability => format_precip_probability(e, ctx),
+                WxParams::Snowfall => format_snowfall(e, ctx),
+                WxParams::PrecipToday => format_precip_today(e, ctx),
+                // wind already shows gusts
+                WxParams::Gust if parameters.contains(&WxParams::Wind) => WeatherData::none(),
+                WxParams::Gust => format_gust(e, ctx),
+                WxParams::CloudCover => format_cloud_cover(e, ctx),
+                WxParams::UvIndex => format_uv_index(e, ctx),
+                WxParams::WetBulb => format_wet_bulb(e, ctx),
+                WxParams::Wbgt => format_wbgt(e, ctx),
+                WxParams::Humidex => format_humidex(e, ctx),
+                WxParams::FrostPoint => format_frost_point(e, ctx),
+                WxParams::PressureAltitude => format_pressure_altitude(e, ctx),
+                WxParams::DensityAltitude => format_density_altitude(e, ctx),
+                WxParams::ComfortIndex => format_comfort(e),
+                WxParams::WorstFactor => format_comfort_worst(e),
+            };
+            (*p, data)
+        })
+        .collect()
+}
 
 pub fn station_line(
     prelude: &str,
-    e: &WxEntryStruct,
-    parameters: &Vec<WxParams>,
-    indoor: bool,
-    db: &BTreeMap<DateTime<Utc>, WxStructDeserialized>,
+    e: &Entry,
+    parameters: &[WxParams],
+    station: &StationConfig,
+    db: &History,
+    config: &Config,
 ) -> Result<String, String> {
-    let mut data_vec: Vec<WeatherData> = vec![];
+    let ctx = LineContext {
+        station,
+        db,
+        config,
+    };
 
     let mut total_string = String::new();
 
-    let (dewpoint, rh) = format_dewpoint(e);
-
-    for p in parameters {
-        match p {
-            WxParams::ApparentTemp => data_vec.push(format_apparent_temp(e)),
-            WxParams::Cape => data_vec.push(format_cape(e)),
-            WxParams::Cloud => data_vec.push(format_cloud(e)),
-            WxParams::Dewpoint => data_vec.push(dewpoint.clone()),
-            WxParams::FlightRules => data_vec.push(format_flight_rules(e)),
-            WxParams::Height500mb => data_vec.push(format_500mb_height(e)),
-            WxParams::Metar => {} // METARs are dealt with separately at the end
-            WxParams::Pressure => data_vec.push(format_pressure(e, db)),
-            WxParams::RelativeHumidity => data_vec.push(rh.clone()),
-            WxParams::Temperature => data_vec.push(format_temp(e, indoor, db)),
-            WxParams::Visibility => data_vec.push(format_visibility(e)),
-            WxParams::Wind => data_vec.push(format_wind(e)),
-            WxParams::Wind250mb => data_vec.push(format_250mb_wind(e)),
-            WxParams::WxCode => data_vec.push(format_wx(e.wx_codes.clone())),
-            WxParams::ComfortIndex => data_vec.push(format_comfort(e)),
-            WxParams::WorstFactor => data_vec.push(format_comfort_worst(e)),
-        }
-    }
+    let data_vec = format_params(e, parameters, &ctx)
+        .into_iter()
+        .map(|(_, d)| d);
 
     total_string.push_str(prelude);
 
@@ -876,7 +1614,7 @@ pub fn station_line(
         };
     }
 
-    if parameters.contains(&WxParams::Metar) {
+    if parameters.contains(&WxParams::Metar) && station.shows(&WxParams::Metar) {
         total_string.push_str(&format_metar(e));
     }
 
@@ -884,3 +1622,195 @@ pub fn station_line(
 
     Ok(total_string)
 }
+
+// TABLE --------------------------------------------------------------------------------------------------------------
+
+// one source's line of the comparison table.
+pub struct TableRow {
+    pub name: String,
+    time: WeatherData,
+    cells: Vec<(WxParams, WeatherData)>,
+    values: Vec<Option<f32>>,
+}
+
+pub fn table_row(
+    name: &str,
+    time: &str,
+    stale: bool,
+    e: &Entry,
+    station: &StationConfig,
+    db: &History,
+    config: &Config,
+) -> TableRow {
+    let ctx = LineContext {
+        station,
+        db,
+        config,
+    };
+
+    let parameters = &config.weather.selected.parameters;
+    let layer = station_layer(station);
+
+    TableRow {
+        name: name.to_string(),
+        time: WeatherData {
+            title: String::new(),
+            text: time.to_string(),
+            style: if stale {
+                Style::warning()
+            } else {
+                String::new()
+            },
+        },
+        cells: format_params(e, parameters, &ctx),
+        values: parameters
+            .iter()
+            .map(|p| station.shows(p).then(|| param_value(e, p, layer)).flatten())
+            .collect(),
+    }
+}
+
+fn column_title(param: &WxParams) -> &'static str {
+    match param {
+        WxParams::FlightRules => "Cat",
+        WxParams::WxCode => "WX",
+        WxParams::Cloud => "Clouds",
+        WxParams::ComfortIndex => "Comfort",
+        WxParams::WorstFactor => "Worst",
+        p => history_label(p).map_or("", |(title, _, _)| title),
+    }
+}
+
+fn median(values: &[f32]) -> f32 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let mid = sorted.len() / 2;
+
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.
+    } else {
+        sorted[mid]
+    }
+}
+
+// pads on what is printed, the styles take no room.
+fn table_cell(text: &str, style: &str, width: usize) -> String {
+    let pad = " ".repeat(width.saturating_sub(text.chars().count()));
+
+    if text.is_empty() {
+        pad
+    } else {
+        format!("{style}{text}{Reset}{pad}")
+    }
+}
+
+// one row per source with a column per parameter. With three or more sources,
+// one that is far from the rest gets marked.
+pub fn station_table(rows: &[TableRow], config: &Config) -> String {
+    let parameters = &config.weather.selected.parameters;
+
+    // columns that have something in them, by index into the parameters
+    let columns: Vec<usize> = (0..parameters.len())
+        .filter(|i| parameters[*i] != WxParams::Metar)
+        .filter(|i| rows.iter().any(|r| !r.cells[*i].1.is_none()))
+        .collect();
+
+    let mut cells: Vec<Vec<(String, String)>> = rows
+        .iter()
+        .map(|r| {
+            let mut line = vec![
+                (r.name.clone(), String::new()),
+                (r.time.text.clone(), r.time.style.clone()),
+            ];
+            line.extend(columns.iter().map(|i| {
+                let data = &r.cells[*i].1;
+                (data.text.clone(), data.style.clone())
+            }));
+            line
+        })
+        .collect();
+
+    let mut spread = vec![
+        ("Spread".to_string(), Bold.to_string()),
+        (String::new(), String::new()),
+    ];
+
+    for (c, i) in columns.iter().enumerate() {
+        let param = &parameters[*i];
+        let (Some(limit), Some((_, unit, decimals))) =
+            (config.weather.spread_limit(param), history_label(param))
+        else {
+            spread.push((String::new(), String::new()));
+            continue;
+        };
+
+        let values: Vec<f32> = rows.iter().filter_map(|r| r.values[*i]).collect();
+
+        if values.len() >= 3 {
+            let median = median(&values);
+            for (r, row) in rows.iter().enumerate() {
+                if let Some(v) = row.values[*i]
+                    && (v - median).abs() > limit
+                {
+                    let cell = &mut cells[r][c + 2];
+                    cell.0.push('!');
+                    cell.1 = Style::warning();
+                }
+            }
+        }
+
+        if values.len() < 2 {
+            spread.push((String::new(), String::new()));
+            continue;
+        }
+
+        let range = values.iter().copied().fold(f32::MIN, f32::max)
+            - values.iter().copied().fold(f32::MAX, f32::min);
+        let style = if range > limit {
+            Style::warning()
+        } else {
+            String::new()
+        };
+        spread.push((format!("{range:.decimals$}{unit}"), style));
+    }
+
+    if config.weather.spread && rows.len() >= 2 {
+        cells.push(spread);
+    }
+
+    let mut header = vec!["Source", "Time"];
+    header.extend(columns.iter().map(|i| column_title(&parameters[*i])));
+
+    let widths: Vec<usize> = header
+        .iter()
+        .enumerate()
+        .map(|(c, h)| {
+            cells
+                .iter()
+                .map(|line| line[c].0.chars().count())
+                .fold(h.chars().count(), usize::max)
+        })
+        .collect();
+
+    let mut s = String::new();
+
+    let line: Vec<String> = header
+        .iter()
+        .zip(&widths)
+        .map(|(h, w)| table_cell(h, &Bold.to_string(), *w))
+        .collect();
+    s.push_str(line.join("  ").trim_end());
+    s.push('\n');
+
+    for row in &cells {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|((text, style), w)| table_cell(text, style, *w))
+            .collect();
+        s.push_str(line.join("  ").trim_end());
+        s.push('\n');
+    }
+
+    s
+}
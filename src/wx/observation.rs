@@ -0,0 +1,252 @@
+use crate::config::{ObsField, ScaleUnit, SourceKind, StationConfig};
+use crate::wx::{Entry, metar, taf};
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use wxer_lib::*;
+
+// OBSERVATIONS ----------------------------------------------------------------
+
+// Values read from a source that isn't wxer, in the units given by
+// `ObsField::unit`, turned into the same entries wxer gives us.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Observation {
+    values: HashMap<ObsField, f32>,
+}
+
+fn rh_to_dewpoint(temp: f32, rh: f32) -> f32 {
+    let t_c = (temp - 32.) * 5. / 9.;
+
+    let beta = 17.62; // constant
+    let lambda = 243.12; // degrees C
+
+    let ln_rh = (rh / 100.).ln();
+    let temp_term = (beta * t_c) / (lambda + t_c);
+    let combined_term = ln_rh + temp_term;
+
+    let dp_c = (lambda * combined_term) / (beta - combined_term);
+
+    dp_c * 9. / 5. + 32.
+}
+
+impl Observation {
+    pub fn set(
+        &mut self,
+        field: ObsField,
+        value: f32,
+        unit: Option<ScaleUnit>,
+    ) -> Result<(), String> {
+        let value = match (field.unit(), unit) {
+            (Some(to), Some(from)) => from
+                .convert(value, to)
+                .ok_or(format!("{from:?} is not a valid unit for {field:?}."))?,
+            _ => value,
+        };
+
+        // loggers write NaN for a missing reading
+        if !value.is_nan() {
+            self.values.insert(field, value);
+        }
+
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    fn get(&self, field: ObsField) -> Option<f32> {
+        self.values.get(&field).copied()
+    }
+
+    // dewpoint if we have it, otherwise work it out from the humidity.
+    fn dewpoint(&self, temp: ObsField, dew: ObsField, rh: ObsField) -> Option<Temperature> {
+        let dewpoint = self.get(dew).or(match (self.get(temp), self.get(rh)) {
+            (Some(t), Some(rh)) if rh > 0. => Some(rh_to_dewpoint(t, rh)),
+            _ => None,
+        });
+
+        dewpoint.map(|d| Temperature::new(d, Fahrenheit))
+    }
+
+    fn precip(&self, field: ObsField) -> Option<Precip> {
+        self.get(field).map(|p| Precip {
+            rain: ProportionalUnit::new(p, Inch),
+            snow: ProportionalUnit::new(0., Inch),
+            unknown: ProportionalUnit::new(0., Inch),
+        })
+    }
+
+    pub fn build(&self, date_time: DateTime<Utc>, station: &'static Station) -> Entry {
+        let mut layers = HashMap::new();
+
+        let mut near_surface = WxEntryLayerStruct::new(Layer::NearSurface, station);
+        near_surface.temperature = self
+            .get(ObsField::Temperature)
+            .map(|t| Temperature::new(t, Fahrenheit));
+        near_surface.dewpoint = self.dewpoint(
+            ObsField::Temperature,
+            ObsField::Dewpoint,
+            ObsField::RelativeHumidity,
+        );
+        near_surface.pressure = self.get(ObsField::Pressure).map(|p| Pressure::new(p, Mbar));
+        near_surface.visibility = self
+            .get(ObsField::Visibility)
+            .map(|v| Distance::new(v, Mile));
+        near_surface.wind = self.get(ObsField::WindSpeed).map(|speed| Wind {
+            direction: self
+                .get(ObsField::WindDirection)
+                .and_then(|d| Direction::from_degrees(d.round() as u16 % 360).ok()),
+            speed: Speed::new(speed, Knots),
+        });
+        layers.insert(Layer::NearSurface, near_surface);
+
+        if let Some(slp) = self.get(ObsField::SeaLevelPressure) {
+            let mut sea_level = WxEntryLayerStruct::new(Layer::SeaLevel, station);
+            sea_level.pressure = Some(Pressure::new(slp, Mbar));
+            layers.insert(Layer::SeaLevel, sea_level);
+        }
+
+        if self.get(ObsField::IndoorTemperature).is_some() {
+            let mut indoor = WxEntryLayerStruct::new(Layer::Indoor, station);
+            indoor.temperature = self
+                .get(ObsField::IndoorTemperature)
+                .map(|t| Temperature::new(t, Fahrenheit));
+            indoor.dewpoint = self.dewpoint(
+                ObsField::IndoorTemperature,
+                ObsField::IndoorDewpoint,
+                ObsField::IndoorRelativeHumidity,
+            );
+            layers.insert(Layer::Indoor, indoor);
+        }
+
+        let wx = WxEntryStruct {
+            date_time,
+            station,
+            layers,
+            cape: None,
+            skycover: None,
+            wx_codes: None,
+            raw_metar: None,
+            precip: self.precip(ObsField::Precip),
+            precip_probability: None,
+            precip_today: self.precip(ObsField::PrecipToday),
+            altimeter: self
+                .get(ObsField::Altimeter)
+                .map(|a| Pressure::new(a, Mbar)),
+        };
+
+        Entry {
+            wx,
+            gust: self.get(ObsField::WindGust).map(|g| Speed::new(g, Knots)),
+            cloud_cover: self
+                .get(ObsField::CloudCover)
+                .map(|c| Fraction::new(c, Percent)),
+            uv_index: self.get(ObsField::UvIndex),
+            apparent_temp: None,
+        }
+    }
+}
+
+// the station a non-wxer source's entries are attached to, from its config.
+pub fn configured_station(
+    source: &str,
+    station_config: &StationConfig,
+) -> Result<&'static Station, String> {
+    let time_zone = source_timezone(station_config)?.unwrap_or(chrono_tz::UTC);
+
+    Ok(Box::leak(Box::new(Station {
+        coords: Coordinates {
+            latitude: station_config.latitude.unwrap_or(0.),
+            longitude: station_config.longitude.unwrap_or(0.),
+        },
+        altitude: Altitude::new(station_config.elevation.unwrap_or(0.), Meter),
+        name: source.to_string(),
+        time_zone,
+    })))
+}
+
+pub fn source_timezone(station_config: &StationConfig) -> Result<Option<Tz>, String> {
+    station_config
+        .timezone
+        .as_ref()
+        .map(|tz| Tz::from_str(tz).map_err(|e| format!("{tz} - {e}")))
+        .transpose()
+}
+
+// timestamps without an offset are in the source's timezone, or local time.
+pub fn parse_local_datetime(
+    s: &str,
+    format: &str,
+    station_config: &StationConfig,
+) -> Result<DateTime<Utc>, String> {
+    let naive = NaiveDateTime::parse_from_str(s.trim(), format).map_err(|e| e.to_string())?;
+
+    let dt = match source_timezone(station_config)? {
+        Some(tz) => tz
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|d| d.with_timezone(&Utc)),
+        None => Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|d| d.with_timezone(&Utc)),
+    };
+
+    dt.ok_or(format!("{s} does not exist in the source's timezone."))
+}
+
+// fills in strftime codes like %Y and %j with the current local date.
+pub fn expand_date(template: &str) -> Result<String, String> {
+    let mut s = String::new();
+    write!(s, "{}", Local::now().format(template))
+        .map_err(|_| format!("\"{template}\" is not a valid date format."))?;
+    Ok(s)
+}
+
+// reads a source's url or file.
+pub async fn source_text(source: &str, station_config: &StationConfig) -> Result<String, String> {
+    let station = station_config.station.as_deref().unwrap_or(source);
+
+    let url = match (
+        &station_config.url,
+        &station_config.path,
+        station_config.kind,
+    ) {
+        (Some(url), _, _) => Some(url.as_str()),
+        (None, None, SourceKind::Metar) => Some(metar::DEFAULT_METAR_URL),
+        (None, None, SourceKind::Taf) => Some(taf::DEFAULT_TAF_URL),
+        _ => None,
+    };
+
+    match (url, &station_config.path) {
+        (Some(url), _) => {
+            let url = expand_date(url)?.replace("{station}", station);
+            let client = reqwest::Client::new();
+            let r = client
+                .get(&url)
+                .timeout(std::time::Duration::from_secs(10))
+                .send()
+                .await
+                .map_err(|e| format!("{url} - {e}"))?;
+
+            if !r.status().is_success() {
+                return Err(format!("{url} - {}", r.status()));
+            }
+
+            r.text().await.map_err(|e| e.to_string())
+        }
+        (None, Some(path)) => {
+            let path = expand_date(path)?.replace("{station}", station);
+            std::fs::read_to_string(&path).map_err(|e| format!("{path} - {e}"))
+        }
+        (None, None) => Err("Source needs either a url or a path.".to_string()),
+    }
+}
@@ -0,0 +1,307 @@
+use crate::common::Style;
+use crate::common::TermStyle::*;
+
+// PRESENT WEATHER -------------------------------------------------------------
+
+// code, and how it reads in a sentence. See FMH-1 chapter 12.
+const DESCRIPTORS: [(&str, &str); 8] = [
+    ("MI", "shallow"),
+    ("PR", "partial"),
+    ("BC", "patches of"),
+    ("DR", "low drifting"),
+    ("BL", "blowing"),
+    ("SH", "showers"),
+    ("TS", "thunderstorm"),
+    ("FZ", "freezing"),
+];
+
+const PHENOMENA: [(&str, &str); 22] = [
+    ("DZ", "drizzle"),
+    ("RA", "rain"),
+    ("SN", "snow"),
+    ("SG", "snow grains"),
+    ("IC", "ice crystals"),
+    ("PL", "ice pellets"),
+    ("GR", "hail"),
+    ("GS", "small hail"),
+    ("UP", "unknown precipitation"),
+    ("BR", "mist"),
+    ("FG", "fog"),
+    ("FU", "smoke"),
+    ("VA", "volcanic ash"),
+    ("DU", "dust"),
+    ("SA", "sand"),
+    ("HZ", "haze"),
+    ("PY", "spray"),
+    ("PO", "dust whirls"),
+    ("SQ", "squalls"),
+    ("FC", "funnel cloud"),
+    ("SS", "sandstorm"),
+    ("DS", "duststorm"),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum WxCategory {
+    Snow,
+    Rain,
+    Severe,
+    Fire,
+    Fog,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Intensity {
+    Light,
+    Moderate,
+    Heavy,
+}
+
+// one group like "-SHRA", "+TSRAGR" or "VCFG".
+#[derive(Debug, Clone, PartialEq)]
+pub struct WxCode {
+    pub intensity: Intensity,
+    pub vicinity: bool,
+    pub descriptor: Option<&'static str>,
+    pub phenomena: Vec<&'static str>,
+}
+
+fn lookup(table: &[(&'static str, &'static str)], code: &str) -> Option<&'static str> {
+    table.iter().find(|(c, _)| *c == code).map(|(c, _)| *c)
+}
+
+fn word(table: &[(&'static str, &'static str)], code: &str) -> &'static str {
+    table
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map_or("", |(_, w)| *w)
+}
+
+// "rain", "rain and hail", "rain, snow and hail"
+fn list(words: &[&str]) -> String {
+    match words {
+        [] => String::new(),
+        [one] => one.to_string(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
+}
+
+impl WxCode {
+    // every part has to be a known code, in order, two letters at a time.
+    pub fn parse(s: &str) -> Option<WxCode> {
+        let (intensity, vicinity, rest) = if let Some(r) = s.strip_prefix('-') {
+            (Intensity::Light, false, r)
+        } else if let Some(r) = s.strip_prefix('+') {
+            (Intensity::Heavy, false, r)
+        } else if let Some(r) = s.strip_prefix("VC") {
+            (Intensity::Moderate, true, r)
+        } else {
+            (Intensity::Moderate, false, s)
+        };
+
+        if rest.len() % 2 != 0 || !rest.is_ascii() {
+            return None;
+        }
+
+        let mut codes = (0..rest.len()).step_by(2).map(|i| &rest[i..i + 2]);
+        let mut descriptor = None;
+        let mut phenomena = vec![];
+
+        if let Some(first) = codes.next() {
+            match lookup(&DESCRIPTORS, first) {
+                Some(d) => descriptor = Some(d),
+                None => phenomena.push(lookup(&PHENOMENA, first)?),
+            }
+        }
+
+        for code in codes {
+            phenomena.push(lookup(&PHENOMENA, code)?);
+        }
+
+        if descriptor.is_none() && phenomena.is_empty() {
+            return None;
+        }
+
+        Some(WxCode {
+            intensity,
+            vicinity,
+            descriptor,
+            phenomena,
+        })
+    }
+
+    pub fn has(&self, code: &str) -> bool {
+        self.descriptor == Some(code) || self.phenomena.contains(&code)
+    }
+
+    pub fn category(&self) -> WxCategory {
+        let any = |codes: &[&str]| codes.iter().any(|c| self.has(c));
+
+        if any(&["FU", "VA"]) {
+            WxCategory::Fire
+        } else if any(&["GR", "TS", "DU", "SA", "SQ", "DS", "SS", "FC", "PO"]) {
+            WxCategory::Severe
+        } else if any(&["SN", "SG", "GS", "PL", "IC"]) {
+            WxCategory::Snow
+        } else if any(&["DZ", "RA", "UP"]) {
+            WxCategory::Rain
+        } else if any(&["FG", "BR", "HZ", "PY"]) {
+            WxCategory::Fog
+        } else {
+            WxCategory::None
+        }
+    }
+
+    // weather nearby isn't falling on us, so it counts as light.
+    fn strength(&self) -> Intensity {
+        if self.vicinity {
+            Intensity::Light
+        } else {
+            self.intensity
+        }
+    }
+
+    // "Heavy thunderstorm with rain and hail", "Light freezing drizzle",
+    // "Showers nearby".
+    pub fn describe(&self) -> String {
+        let phenomena: Vec<&str> = self.phenomena.iter().map(|p| word(&PHENOMENA, p)).collect();
+        let phenomena = list(&phenomena);
+
+        let intensity = match self.intensity {
+            Intensity::Light => "light ",
+            Intensity::Heavy => "heavy ",
+            Intensity::Moderate => "",
+        };
+
+        let mut s = match self.descriptor {
+            // heavy funnel clouds are tornadoes
+            None if self.intensity == Intensity::Heavy && self.has("FC") => {
+                "tornado or waterspout".to_string()
+            }
+            None => format!("{intensity}{phenomena}"),
+            Some("TS") if phenomena.is_empty() => format!("{intensity}thunderstorm"),
+            Some("TS") => format!("{intensity}thunderstorm with {phenomena}"),
+            Some("SH") if phenomena.is_empty() => "showers".to_string(),
+            Some("SH") => format!("{intensity}{phenomena} showers"),
+            Some(d) => format!("{intensity}{} {phenomena}", word(&DESCRIPTORS, d)),
+        };
+
+        if self.vicinity {
+            s.push_str(" nearby");
+        }
+
+        let mut chars = s.trim().chars();
+        match chars.next() {
+            Some(c) => c.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    }
+}
+
+// the worst weather decides the color, heavier being worse within a category.
+pub fn wx_style(codes: &[WxCode]) -> String {
+    let rank = |c: &WxCategory| match c {
+        WxCategory::Fire => 5,
+        WxCategory::Severe => 4,
+        WxCategory::Snow => 3,
+        WxCategory::Rain => 2,
+        WxCategory::Fog => 1,
+        WxCategory::None => 0,
+    };
+
+    let worst = codes
+        .iter()
+        .map(|c| (c.category(), c.strength()))
+        .max_by_key(|(cat, strength)| (rank(cat), *strength));
+
+    use Intensity::*;
+
+    match worst {
+        Some((WxCategory::Fire, _)) => Style::string(&[RedBg, White, Bold]),
+        Some((WxCategory::Severe, Light)) => Style::string(&[Yellow, Bold]),
+        Some((WxCategory::Severe, Moderate)) => Style::string(&[YellowBg, Black, Bold]),
+        Some((WxCategory::Severe, Heavy)) => Style::string(&[RedBg, White, Bold]),
+        Some((WxCategory::Snow, Light)) => Style::string(&[Cyan, Bold]),
+        Some((WxCategory::Snow, Moderate)) => Style::string(&[WhiteBg, Blue, Bold]),
+        Some((WxCategory::Snow, Heavy)) => Style::string(&[BlueBg, White, Bold]),
+        Some((WxCategory::Rain, Light)) => Style::string(&[Blue, Bold]),
+        Some((WxCategory::Rain, Moderate)) => Style::string(&[BlueBg, Black, Bold]),
+        Some((WxCategory::Rain, Heavy)) => Style::string(&[PurpleBg, White, Bold]),
+        Some((WxCategory::Fog, _)) => Style::string(&[WhiteBg, Black, Bold]),
+        Some((WxCategory::None, _)) | None => Style::string(&[Reset]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn described(s: &str) -> String {
+        WxCode::parse(s).unwrap().describe()
+    }
+
+    #[test]
+    fn parses_groups() {
+        assert_eq!(
+            WxCode::parse("-SHRA"),
+            Some(WxCode {
+                intensity: Intensity::Light,
+                vicinity: false,
+                descriptor: Some("SH"),
+                phenomena: vec!["RA"],
+            })
+        );
+        assert_eq!(
+            WxCode::parse("+TSRAGR"),
+            Some(WxCode {
+                intensity: Intensity::Heavy,
+                vicinity: false,
+                descriptor: Some("TS"),
+                phenomena: vec!["RA", "GR"],
+            })
+        );
+        assert_eq!(
+            WxCode::parse("VCSH"),
+            Some(WxCode {
+                intensity: Intensity::Moderate,
+                vicinity: true,
+                descriptor: Some("SH"),
+                phenomena: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_other_groups() {
+        for s in ["KPSM", "AO2", "RMK", "SLP132", "", "-", "RAX", "+SH1"] {
+            assert_eq!(WxCode::parse(s), None, "{s}");
+        }
+    }
+
+    #[test]
+    fn describes() {
+        assert_eq!(described("-SHRA"), "Light rain showers");
+        assert_eq!(
+            described("+TSRAGR"),
+            "Heavy thunderstorm with rain and hail"
+        );
+        assert_eq!(described("VCSH"), "Showers nearby");
+        assert_eq!(described("FZFG"), "Freezing fog");
+        assert_eq!(described("-FZDZ"), "Light freezing drizzle");
+        assert_eq!(described("+FC"), "Tornado or waterspout");
+        assert_eq!(described("RASNPL"), "Rain, snow and ice pellets");
+        assert_eq!(described("BR"), "Mist");
+    }
+
+    #[test]
+    fn categories() {
+        let category = |s| WxCode::parse(s).unwrap().category();
+
+        assert_eq!(category("-SHRA"), WxCategory::Rain);
+        assert_eq!(category("+TSRAGR"), WxCategory::Severe);
+        assert_eq!(category("-RASN"), WxCategory::Snow);
+        assert_eq!(category("FZFG"), WxCategory::Fog);
+        assert_eq!(category("FU"), WxCategory::Fire);
+        assert_eq!(category("VCSH"), WxCategory::None);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::common;
+use crate::config::StationConfig;
+use crate::wx::History;
+use crate::wx::observation::{Observation, configured_station, parse_local_datetime};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Duration, Utc};
+use serde_json::Value;
+
+// PERSONAL WEATHER STATIONS ---------------------------------------------------
+
+// Consoles only give the current reading, so past readings are kept in the
+// cache to give the trends and sparklines something to work with.
+type Readings = BTreeMap<DateTime<Utc>, Observation>;
+
+fn readings_path(source: &str) -> anyhow::Result<PathBuf> {
+    let name: String = source
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    Ok(common::cache_dir()?.join(format!("pws_{name}.json")))
+}
+
+fn load_readings(source: &str) -> Readings {
+    readings_path(source)
+        .ok()
+        .and_then(|p| fs::read_to_string(p).ok())
+        .and_then(|t| serde_json::from_str(&t).ok())
+        .unwrap_or_default()
+}
+
+fn save_readings(source: &str, readings: &Readings) -> anyhow::Result<()> {
+    let path = readings_path(source)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, serde_json::to_string(readings)?)?;
+
+    Ok(())
+}
+
+// consoles often send numbers as strings, sometimes with the unit attached
+// like "72.5 F" or "45%".
+fn number(v: &Value) -> Option<f32> {
+    match v {
+        Value::Number(n) => n.as_f64().map(|n| n as f32),
+        Value::String(s) => {
+            let s = s.trim();
+            let end = s
+                .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+                .unwrap_or(s.len());
+            s[..end].parse().ok()
+        }
+        _ => None,
+    }
+}
+
+fn reading_time(json: &Value, station_config: &StationConfig) -> Result<DateTime<Utc>, String> {
+    let Some(pointer) = &station_config.datetime_pointer else {
+        return Ok(Utc::now());
+    };
+
+    let v = json
+        .pointer(pointer)
+        .ok_or(format!("Nothing at {pointer}."))?;
+
+    match (v, &station_config.datetime_format) {
+        (Value::String(s), Some(format)) => parse_local_datetime(s, format, station_config),
+        (Value::String(s), None) if epoch(v).is_none() => DateTime::parse_from_rfc3339(s)
+            .map(|d| d.with_timezone(&Utc))
+            .map_err(|e| format!("{s} - {e}")),
+        (v, _) => {
+            let t = epoch(v).ok_or(format!("{v} is not a time."))?;
+            // seconds or milliseconds since the epoch
+            let t = if t > 100_000_000_000 {
+                DateTime::from_timestamp_millis(t)
+            } else {
+                DateTime::from_timestamp(t, 0)
+            };
+            t.ok_or(format!("{v} is not a time."))
+        }
+    }
+}
+
+// whole seconds or milliseconds, read as integers. They're too big for
+// `number`, an f32 would be off by up to a minute.
+fn epoch(v: &Value) -> Option<i64> {
+    match v {
+        Value::Number(n) => n.as_i64().or_else(|| n.as_f64().map(|t| t as i64)),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+pub fn json_history(
+    text: &str,
+    source: &str,
+    station_config: &StationConfig,
+) -> Result<History, String> {
+    if station_config.fields.is_empty() {
+        return Err(format!("{source} does not have any fields configured."));
+    }
+
+    let json = common::parse_loose_json(text)?;
+    let date_time = reading_time(&json, station_config)?;
+
+    let mut obs = Observation::default();
+    for (pointer, mapping) in &station_config.fields {
+        if let Some(v) = json.pointer(pointer).and_then(number) {
+            obs.set(mapping.field, v, mapping.unit)?;
+        }
+    }
+
+    if obs.is_empty() {
+        return Err(format!("{source} did not have any data."));
+    }
+
+    let mut readings = load_readings(source);
+    readings.insert(date_time, obs);
+
+    let keep = Duration::hours(station_config.window.unwrap_or(24));
+    let readings = readings.split_off(&(date_time - keep));
+
+    // the next run can do without them, so failing to save isn't an error
+    let _ = save_readings(source, &readings);
+
+    let station = configured_station(source, station_config)?;
+
+    Ok(readings
+        .into_iter()
+        .map(|(t, obs)| (t, obs.build(t, station)))
+        .collect())
+}
@@ -0,0 +1,547 @@
+use crate::common;
+use crate::config::{Config, SourceKind, StationConfig, WxParams};
+use crate::wx::metar::{
+    ReportBody, day_time, is_wx_code, parse_cloud, parse_visibility, parse_wind,
+};
+use crate::wx::observation::{configured_station, source_text, source_timezone};
+use crate::wx::{Entry, FlightRules, flight_rules, station_line};
+use common::TermStyle::*;
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use chrono_tz::Tz;
+
+use wxer_lib::*;
+
+// TAF -------------------------------------------------------------------------
+
+// aviationweather.gov's data api, {station} is replaced with the station id.
+pub const DEFAULT_TAF_URL: &str =
+    "https://aviationweather.gov/api/data/taf?ids={station}&format=raw";
+
+// enough to tell the flight category, and why.
+const TAF_PARAMETERS: [WxParams; 5] = [
+    WxParams::FlightRules,
+    WxParams::Wind,
+    WxParams::Visibility,
+    WxParams::WxCode,
+    WxParams::Cloud,
+];
+
+// The groups a change can replace. A change replaces a whole group, all of the
+// clouds at once for example, so they're kept as tokens until the conditions
+// for a period are settled.
+#[derive(Clone, Default)]
+struct Conditions<'a> {
+    wind: Option<&'a str>,
+    visibility: Option<&'a [&'a str]>,
+    // empty after NSW, no significant weather
+    wx: Option<Vec<&'a str>>,
+    clouds: Option<Vec<&'a str>>,
+}
+
+impl<'a> Conditions<'a> {
+    fn parse(tokens: &'a [&'a str]) -> Conditions<'a> {
+        let mut c = Conditions::default();
+
+        let mut i = 0;
+        while i < tokens.len() {
+            let t = tokens[i];
+
+            if parse_wind(t).is_some() {
+                c.wind = Some(t);
+            } else if t == "CAVOK" {
+                c.visibility = Some(&tokens[i..i + 1]);
+                c.wx = Some(vec![]);
+                c.clouds = Some(vec!["NSC"]);
+            } else if let Some((_, _, used)) = parse_visibility(&tokens[i..]) {
+                c.visibility = Some(&tokens[i..i + used]);
+                i += used;
+                continue;
+            } else if parse_cloud(t).is_some() {
+                c.clouds.get_or_insert_with(Vec::new).push(t);
+            } else if t == "NSW" {
+                c.wx = Some(vec![]);
+            } else if is_wx_code(t) {
+                c.wx.get_or_insert_with(Vec::new).push(t);
+            }
+            // wind shear, temperatures and the like are skipped
+
+            i += 1;
+        }
+
+        c
+    }
+
+    // these conditions, with whatever `change` says replacing them.
+    fn overlay(&self, change: &Conditions<'a>) -> Conditions<'a> {
+        Conditions {
+            wind: change.wind.or(self.wind),
+            visibility: change.visibility.or(self.visibility),
+            wx: change.wx.clone().or_else(|| self.wx.clone()),
+            clouds: change.clouds.clone().or_else(|| self.clouds.clone()),
+        }
+    }
+
+    fn entry(&self, date_time: DateTime<Utc>, station: &'static Station) -> Result<Entry, String> {
+        let mut body = ReportBody::default();
+
+        if let Some(wind) = self.wind {
+            body.group(&[wind])?;
+        }
+        if let Some(visibility) = self.visibility {
+            body.group(visibility)?;
+        }
+        for t in self.wx.iter().chain(self.clouds.iter()).flatten() {
+            body.group(&[t])?;
+        }
+
+        Ok(body.build(date_time, station))
+    }
+}
+
+enum Change {
+    Initial,
+    From(DateTime<Utc>),
+    Becoming(DateTime<Utc>),
+    // TEMPO, PROB30 or PROB30 TEMPO, until the end time
+    Temporary(String, DateTime<Utc>, DateTime<Utc>),
+}
+
+// one stretch of the forecast.
+pub struct TafPeriod {
+    // "FM", "BECMG", "TEMPO", "PROB30" and so on, empty for the first
+    pub change: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub entry: Entry,
+}
+
+// "1818/1918"
+fn time_range(t: &str, now: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let (start, end) = t.split_once('/')?;
+    Some((day_time(start, now)?, day_time(end, now)?))
+}
+
+// One report, from the station id on: "KPSM 181720Z 1818/1918 27015G25KT P6SM
+// SCT040 FM182200 28010KT P6SM SKC TEMPO 1900/1904 3SM -SHRA BKN015".
+fn decode_taf(
+    tokens: &[&str],
+    station: &'static Station,
+    now: DateTime<Utc>,
+) -> Result<Vec<TafPeriod>, String> {
+    let mut i = 1;
+
+    // the issue time is sometimes left off
+    if tokens.get(i).is_some_and(|t| t.ends_with('Z')) {
+        i += 1;
+    }
+
+    let (valid_from, valid_to) = tokens
+        .get(i)
+        .and_then(|t| time_range(t, now))
+        .ok_or(format!(
+            "Could not read when the {} TAF is valid.",
+            tokens[0]
+        ))?;
+    i += 1;
+
+    let tokens = &tokens[i..];
+    let tokens = match tokens.iter().position(|t| *t == "RMK") {
+        Some(end) => &tokens[..end],
+        None => tokens,
+    };
+
+    // split into the change groups, each with its own tokens
+    let mut changes: Vec<(Change, Vec<&str>)> = vec![(Change::Initial, vec![])];
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let t = tokens[i];
+
+        let from = t
+            .strip_prefix("FM")
+            .filter(|t| t.len() == 6)
+            .and_then(|t| day_time(t, now));
+
+        if let Some(from) = from {
+            changes.push((Change::From(from), vec![]));
+            i += 1;
+        } else if t == "BECMG"
+            && let Some((start, _)) = tokens.get(i + 1).and_then(|t| time_range(t, now))
+        {
+            changes.push((Change::Becoming(start), vec![]));
+            i += 2;
+        } else if t == "TEMPO"
+            && let Some((start, end)) = tokens.get(i + 1).and_then(|t| time_range(t, now))
+        {
+            changes.push((Change::Temporary(t.to_string(), start, end), vec![]));
+            i += 2;
+        } else if t.starts_with("PROB") {
+            // PROB30 1900/1904, or PROB30 TEMPO 1900/1904
+            let (label, used) = match tokens.get(i + 1) {
+                Some(&"TEMPO") => (format!("{t} TEMPO"), 2),
+                _ => (t.to_string(), 1),
+            };
+
+            match tokens.get(i + used).and_then(|t| time_range(t, now)) {
+                Some((start, end)) => {
+                    changes.push((Change::Temporary(label, start, end), vec![]));
+                    i += used + 1;
+                }
+                None => i += 1,
+            }
+        } else {
+            if let Some((_, group)) = changes.last_mut() {
+                group.push(t);
+            }
+            i += 1;
+        }
+    }
+
+    // the prevailing conditions, and the temporary ones on top of them
+    let mut prevailing: Vec<(String, DateTime<Utc>, Conditions)> = vec![];
+    let mut temporary: Vec<(String, DateTime<Utc>, DateTime<Utc>, Conditions)> = vec![];
+
+    for (change, group) in &changes {
+        let c = Conditions::parse(group);
+        let current = prevailing
+            .last()
+            .map(|(_, _, c)| c.clone())
+            .unwrap_or_default();
+
+        match change {
+            Change::Initial => prevailing.push((String::new(), valid_from, c)),
+            Change::From(t) => prevailing.push(("FM".to_string(), *t, c)),
+            Change::Becoming(t) => prevailing.push(("BECMG".to_string(), *t, current.overlay(&c))),
+            Change::Temporary(label, start, end) => {
+                temporary.push((label.clone(), *start, *end, current.overlay(&c)))
+            }
+        }
+    }
+
+    let mut periods = vec![];
+
+    for (i, (change, start, c)) in prevailing.iter().enumerate() {
+        let end = prevailing.get(i + 1).map_or(valid_to, |(_, t, _)| *t);
+        periods.push(TafPeriod {
+            change: change.clone(),
+            start: *start,
+            end,
+            entry: c.entry(*start, station)?,
+        });
+    }
+
+    for (change, start, end, c) in temporary {
+        periods.push(TafPeriod {
+            change,
+            start,
+            end,
+            entry: c.entry(start, station)?,
+        });
+    }
+
+    // stable, so temporary changes come after the prevailing ones they start with
+    periods.sort_by_key(|p| p.start);
+
+    Ok(periods)
+}
+
+// the latest TAF for the station in a text response or file.
+pub fn taf_periods(
+    text: &str,
+    source: &str,
+    station_config: &StationConfig,
+) -> Result<Vec<TafPeriod>, String> {
+    let station_id = station_config.station.as_deref().unwrap_or(source);
+    let station = configured_station(station_id, station_config)?;
+
+    let tokens: Vec<&str> = text
+        .split_ascii_whitespace()
+        .map(|t| t.trim_end_matches('='))
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    // reports start at the station id, the TAF and AMD before it are left out
+    let starts: Vec<usize> = tokens
+        .iter()
+        .enumerate()
+        .filter(|(i, t)| {
+            **t == station_id
+                && tokens
+                    .get(i + 1)
+                    .is_some_and(|n| n.ends_with('Z') || n.contains('/'))
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    let start = *starts.last().ok_or(format!("No TAF for {station_id}."))?;
+    let end = tokens[start..]
+        .iter()
+        .position(|t| matches!(*t, "TAF" | "AMD" | "COR"))
+        .map_or(tokens.len(), |end| start + end);
+
+    decode_taf(&tokens[start..end], station, Utc::now())
+}
+
+fn local_time(dt: DateTime<Utc>, tz: Option<Tz>) -> DateTime<FixedOffset> {
+    match tz {
+        Some(tz) => dt.with_timezone(&tz).fixed_offset(),
+        None => dt.with_timezone(&Local).fixed_offset(),
+    }
+}
+
+// one line per period, under a heading that says when it first goes IFR.
+pub async fn taf_section(source: &str, config: &Config) -> String {
+    // anything but a taf source of the same name is taken as an ICAO id
+    let mut icao = StationConfig::default();
+    icao.kind = SourceKind::Taf;
+    let station_config = match config.weather.stations.get(source) {
+        Some(station) if station.kind == SourceKind::Taf => station,
+        _ => &icao,
+    };
+    let name = station_config.name.as_deref().unwrap_or(source);
+
+    let periods = async {
+        let tz = source_timezone(station_config)?;
+        let text = source_text(source, station_config).await?;
+        Ok::<_, String>((taf_periods(&text, source, station_config)?, tz))
+    };
+
+    let (periods, tz) = match periods.await {
+        Ok(p) => p,
+        Err(e) => {
+            return format!(
+                "{Bold}{name} TAF:{Reset} {}{}{Reset}\n",
+                common::Style::error(),
+                e.trim_end()
+            );
+        }
+    };
+
+    let ifr = periods.iter().find(|p| {
+        matches!(
+            flight_rules(&p.entry),
+            Some(FlightRules::IFR | FlightRules::LIFR)
+        )
+    });
+
+    let mut s = match ifr {
+        Some(p) => format!(
+            "{Bold}{name} TAF:{Reset} {}IFR{Reset} or worse from {}\n",
+            FlightRules::IFR.style(),
+            local_time(p.start, tz).format("%a %d %l%p")
+        ),
+        None => format!("{Bold}{name} TAF:{Reset}\n"),
+    };
+
+    for p in &periods {
+        let start = local_time(p.start, tz);
+        let end = local_time(p.end, tz);
+
+        let end = if end.date_naive() == start.date_naive() {
+            end.format("%l%p")
+        } else {
+            end.format("%a %l%p")
+        };
+
+        let prelude = format!(
+            "  {}-{}{}:",
+            start.format("%a %d %l%p"),
+            end.to_string().trim(),
+            match p.change.as_str() {
+                "" => String::new(),
+                change => format!(" {Bold}{change}{Reset}"),
+            }
+        );
+
+        match station_line(
+            &prelude,
+            &p.entry,
+            &TAF_PARAMETERS,
+            &StationConfig::default(),
+            &BTreeMap::new(),
+            config,
+        ) {
+            Ok(line) => s.push_str(&line),
+            Err(e) => s.push_str(&format!("{prelude} {}{e}{Reset}\n", common::Style::error())),
+        }
+    }
+
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    fn at(d: u32, h: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, d, h, 0, 0).unwrap()
+    }
+
+    fn decode(raw: &str) -> Vec<TafPeriod> {
+        let station = configured_station("KPSM", &StationConfig::default()).unwrap();
+        let tokens: Vec<&str> = raw
+            .split_ascii_whitespace()
+            .map(|t| t.trim_end_matches('='))
+            .collect();
+
+        let now = Utc.with_ymd_and_hms(2026, 10, 18, 17, 30, 0).unwrap();
+        decode_taf(&tokens, station, now).unwrap()
+    }
+
+    // to the tenth, converting to the same unit isn't exact
+    fn tenths(v: f32) -> f32 {
+        (v * 10.).round() / 10.
+    }
+
+    fn wind(p: &TafPeriod) -> Option<f32> {
+        let surface = &p.entry.layers[&Layer::NearSurface];
+        surface.wind.map(|w| tenths(w.speed.value_in(Knots)))
+    }
+
+    fn visibility(p: &TafPeriod) -> Option<f32> {
+        let surface = &p.entry.layers[&Layer::NearSurface];
+        surface.visibility.map(|v| tenths(v.value_in(Mile)))
+    }
+
+    const TAF: &str = "KPSM 181720Z 1818/1918 27015G25KT P6SM SCT040 \
+        FM182200 28010KT P6SM SKC \
+        BECMG 1902/1904 OVC008 \
+        TEMPO 1904/1908 2SM -SHRA BR \
+        PROB30 TEMPO 1910/1914 1/2SM +TSRA OVC004CB \
+        FM191600 VRB03KT 6SM BR BKN015 \
+        RMK NXT FCST BY 00Z=";
+
+    #[test]
+    fn change_groups() {
+        let periods = decode(TAF);
+
+        let changes: Vec<&str> = periods.iter().map(|p| p.change.as_str()).collect();
+        assert_eq!(changes, ["", "FM", "BECMG", "TEMPO", "PROB30 TEMPO", "FM"]);
+
+        let times: Vec<_> = periods.iter().map(|p| (p.start, p.end)).collect();
+        assert_eq!(
+            times,
+            [
+                (at(18, 18), at(18, 22)),
+                (at(18, 22), at(19, 2)),
+                // prevailing until the next FM
+                (at(19, 2), at(19, 16)),
+                (at(19, 4), at(19, 8)),
+                (at(19, 10), at(19, 14)),
+                // the last one lasts until the TAF runs out
+                (at(19, 16), at(19, 18)),
+            ]
+        );
+    }
+
+    #[test]
+    fn flight_rules_by_period() {
+        let periods = decode(TAF);
+
+        let rules: Vec<_> = periods.iter().map(|p| flight_rules(&p.entry)).collect();
+        assert_eq!(
+            rules,
+            [
+                Some(FlightRules::VFR),
+                Some(FlightRules::VFR),
+                Some(FlightRules::IFR),
+                Some(FlightRules::IFR),
+                Some(FlightRules::LIFR),
+                Some(FlightRules::MVFR),
+            ]
+        );
+    }
+
+    #[test]
+    fn becmg_overlays_the_prevailing_conditions() {
+        let periods = decode(TAF);
+        let becmg = &periods[2];
+
+        // only the clouds change, the wind and visibility carry over from FM
+        assert_eq!(wind(becmg), Some(10.));
+        assert_eq!(visibility(becmg), Some(6.));
+        match &becmg.entry.skycover {
+            Some(SkyCoverage::Cloudy(layers)) => {
+                let heights: Vec<u32> = layers.iter().map(|l| l.height).collect();
+                assert_eq!(heights, [800]);
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn tempo_overlays_what_prevails_when_it_starts() {
+        let periods = decode(TAF);
+
+        let tempo = &periods[3];
+        assert_eq!(wind(tempo), Some(10.));
+        assert_eq!(visibility(tempo), Some(2.));
+        assert_eq!(
+            tempo.entry.wx_codes.as_deref(),
+            Some(&["-SHRA".to_string(), "BR".to_string()][..])
+        );
+
+        let prob = &periods[4];
+        assert_eq!(visibility(prob), Some(0.5));
+        assert_eq!(
+            prob.entry.wx_codes.as_deref(),
+            Some(&["+TSRA".to_string()][..])
+        );
+
+        // a temporary change doesn't carry over into the next period
+        let from = &periods[5];
+        assert_eq!(wind(from), Some(3.));
+        assert_eq!(
+            from.entry.wx_codes.as_deref(),
+            Some(&["BR".to_string()][..])
+        );
+    }
+
+    #[test]
+    fn prob_without_tempo() {
+        let periods = decode("KPSM 181720Z 1818/1918 27015KT P6SM SCT040 PROB40 1900/1904 3SM BR");
+
+        let changes: Vec<&str> = periods.iter().map(|p| p.change.as_str()).collect();
+        assert_eq!(changes, ["", "PROB40"]);
+        assert_eq!(visibility(&periods[1]), Some(3.));
+    }
+
+    #[test]
+    fn valid_until_hour_24() {
+        let periods = decode(
+            "KPSM 181720Z 1818/1924 27015KT P6SM SCT040 \
+             FM191800 30008KT P6SM SKC TEMPO 1920/1924 5SM -RA",
+        );
+
+        assert_eq!(periods[1].start, at(19, 18));
+        assert_eq!(periods[1].end, at(20, 0));
+        assert_eq!((periods[2].start, periods[2].end), (at(19, 20), at(20, 0)));
+    }
+
+    #[test]
+    fn cavok_clears_weather_and_clouds() {
+        let periods = decode(
+            "EGLL 181700Z 1818/1924 24012KT 9999 -RA BKN012 \
+             BECMG 1900/1903 CAVOK",
+        );
+
+        let becmg = &periods[1];
+        assert_eq!(wind(becmg), Some(12.));
+        assert!(matches!(becmg.entry.skycover, Some(SkyCoverage::Clear)));
+        assert_eq!(becmg.entry.wx_codes.as_deref(), Some(&[][..]));
+    }
+
+    #[test]
+    fn missing_valid_period() {
+        let station = configured_station("KPSM", &StationConfig::default()).unwrap();
+        let tokens = ["KPSM", "181720Z", "27015KT", "P6SM"];
+
+        let e = decode_taf(&tokens, station, Utc::now()).err().unwrap();
+        assert_eq!(e, "Could not read when the KPSM TAF is valid.");
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use chrono::NaiveDate;
 use csv;
 use serde::Deserialize;
@@ -11,24 +13,28 @@ pub async fn teleconnections(config: &Config) {
     }
 
     match teleconnections_handler(config).await {
-        Ok(s) => {
-            println!("{}", s)
-        }
-        Err(e) => {
-            println!("{}{}", common::title("TELECONNECTIONS"), e)
-        }
+        Ok(s) => config.output.print(&s),
+        Err(e) => config
+            .output
+            .print(&format!("{}{}", common::title("TELECONNECTIONS"), e)),
     }
 }
 
-async fn get_enso() -> Result<(Vec<f32>, String), String> {
+async fn get_enso(config: &Config) -> Result<(Vec<f32>, String), String> {
     let url = "https://psl.noaa.gov/enso/mei/data/meiv2.data";
 
+    let start = Instant::now();
     let data = reqwest::get(url)
         .await
         .map_err(|x| x.to_string())?
         .text()
         .await
         .map_err(|x| x.to_string())?;
+    config
+        .timings
+        .network(Modules::Teleconnections, start, data.len());
+
+    let start = Instant::now();
 
     // let mut reader = csv::ReaderBuilder::new()
     //                                     .delimiter(b'\t')
@@ -78,6 +84,8 @@ async fn get_enso() -> Result<(Vec<f32>, String), String> {
         };
     }
 
+    config.timings.parse(Modules::Teleconnections, start);
+
     Ok((all_months, month_name.to_string()))
 }
 
@@ -142,13 +150,19 @@ struct NaoRecord {
     valid_time: NaiveDate,
 }
 
-async fn get_nao() -> Result<BTreeMap<NaiveDate, f32>, String> {
+async fn get_nao(config: &Config) -> Result<BTreeMap<NaiveDate, f32>, String> {
     let url = "https://ftp.cpc.ncep.noaa.gov/cwlinks/norm.daily.nao.gfs.z500.120days.csv";
     // this thing is overkill
+    let start = Instant::now();
     let data = reqwest::get(url).await.map_err(|e| e.to_string())?;
     // let bytes = data.bytes().await.map_err(|e| e.to_string())?;
 
     let text = data.text().await.map_err(|e| e.to_string())?;
+    config
+        .timings
+        .network(Modules::Teleconnections, start, text.len());
+
+    let start = Instant::now();
 
     let mut map = BTreeMap::new();
 
@@ -168,6 +182,8 @@ async fn get_nao() -> Result<BTreeMap<NaiveDate, f32>, String> {
         }
     }
 
+    config.timings.parse(Modules::Teleconnections, start);
+
     Ok(map)
 }
 
@@ -213,13 +229,17 @@ async fn teleconnections_handler(config: &Config) -> Result<String, String> {
     let mut s = common::title("TELECONNECTIONS");
 
     if config.teleconnections.values.contains(&Enso) {
-        let enso = get_enso().await?;
+        let enso = get_enso(config).await?;
+        let start = Instant::now();
         s.push_str(&format_enso(enso)?);
+        config.timings.render(Modules::Teleconnections, start);
     }
 
     if config.teleconnections.values.contains(&Nao) {
-        let nao: BTreeMap<NaiveDate, f32> = get_nao().await?;
+        let nao: BTreeMap<NaiveDate, f32> = get_nao(config).await?;
+        let start = Instant::now();
         s.push_str(&format_nao(nao)?);
+        config.timings.render(Modules::Teleconnections, start);
     }
 
     Ok(s)
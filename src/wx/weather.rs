@@ -1,11 +1,15 @@
 use crate::common;
-use crate::config::Config;
+use crate::config::{ConditionsLayout, Config, SourceKind, StationConfig, WxerRange};
+use crate::wx::observation::{source_text, source_timezone};
+use crate::wx::wxer::wxer_query;
 use crate::wx::*;
+use common::TermStyle::*;
 
 use std::collections::BTreeMap;
-use std::time::Duration;
+use std::time::Instant;
 
 use chrono::{DateTime, Local, Utc};
+use futures::future::join_all;
 use serde::Deserialize;
 use serde_json;
 
@@ -25,93 +29,290 @@ use wxer_lib::*;
 //     }
 // }
 
-async fn wxer_query(loc: &str, time: &str, config: &Config) -> Result<String, String> {
-    let addresses = &config.wxer.addresses;
+#[derive(Debug, Clone, Deserialize)]
+struct WxerResponse {
+    #[allow(dead_code)]
+    station: Station,
+    data: BTreeMap<DateTime<Utc>, WxStructDeserialized>,
+}
 
-    let client = reqwest::Client::new();
+struct StationReport {
+    text: String,
+    // sparklines and the like, shown under the table in that layout
+    extra: String,
+    row: Option<TableRow>,
+    stale: bool,
+}
 
-    let mut err_string = String::new();
+// wxer's history for a source, with the address that served it.
+async fn wxer_history(
+    x: &str,
+    station_config: &StationConfig,
+    config: &Config,
+) -> Result<(History, Option<String>), String> {
+    let start = Instant::now();
+    let (conditions, mirror) = wxer_query(x, station_config.range.name(), config).await?;
+    config
+        .timings
+        .network(Modules::CurrentConditions, start, conditions.len());
 
-    for addr in addresses {
-        let url = format!("{addr}/{loc}/{time}.json");
-        // dbg!(&url);
-        let q = client
-            .get(&url)
-            .timeout(Duration::from_secs(10))
-            .send()
-            .await;
+    let start = Instant::now();
+    let data: WxerResponse = serde_json::from_str(&conditions).map_err(|e| e.to_string())?;
 
-        match q {
-            Ok(r) => {
-                if r.status().is_success() {
-                    return r.text().await.map_err(|e| e.to_string());
-                } else {
-                    err_string.push_str(&format!(
-                        "{url} - {}, {}\n",
-                        r.status().as_u16(),
-                        r.status().as_str()
-                    ))
-                }
-            }
+    let (latest_time, latest) = data
+        .data
+        .last_key_value()
+        .ok_or(format!("{} did not have any data.", x))?;
+
+    let mut station = latest.station.clone();
+    if let Some(elevation) = station_config.elevation {
+        station.altitude = Altitude::new(elevation, Meter);
+    }
+    let station: &'static Station = Box::leak(Box::new(station));
 
-            Err(err) => {
-                err_string.push_str(&format!("{url} - {:?}, {err}\n", err.status()));
+    // the latest entry has to convert, older ones only feed the trends.
+    let since = match station_config.window {
+        Some(hours) => *latest_time - chrono::Duration::hours(hours),
+        None => DateTime::<Utc>::MIN_UTC,
+    };
+
+    let mut db = History::new();
+    for (time, d) in data.data.range(since..) {
+        match d.to_struct() {
+            Ok(mut entry) => {
+                entry.station = station;
+                db.insert(*time, entry.into());
+            }
+            Err(_) if time == latest_time => {
+                return Err("Could not convert to struct.".to_string());
             }
+            Err(_) => {}
         }
     }
+    config.timings.parse(Modules::CurrentConditions, start);
 
-    Err(format!(
-        "None of the addresses responded successfully!\n{err_string}"
-    ))
+    Ok((db, Some(mirror)))
 }
 
-#[derive(Debug, Clone, Deserialize)]
-struct WxerResponse {
-    #[allow(dead_code)]
-    station: Station,
-    data: BTreeMap<DateTime<Utc>, WxStructDeserialized>,
+async fn station_history(
+    x: &str,
+    station_config: &StationConfig,
+    config: &Config,
+) -> Result<(History, Option<String>), String> {
+    match station_config.kind {
+        SourceKind::Wxer => return wxer_history(x, station_config, config).await,
+        SourceKind::Taf => return Err(format!("{x} is a TAF, add it to [forecast] tafs.")),
+        _ => {}
+    }
+
+    let start = Instant::now();
+    let text = source_text(x, station_config).await?;
+    config
+        .timings
+        .network(Modules::CurrentConditions, start, text.len());
+
+    let start = Instant::now();
+    let mut db = match station_config.kind {
+        SourceKind::Csv => datalogger::csv_history(&text, x, station_config)?,
+        SourceKind::Metar => metar::metar_history(&text, x, station_config)?,
+        SourceKind::Json => pws::json_history(&text, x, station_config)?,
+        SourceKind::Wxer | SourceKind::Taf => unreachable!(),
+    };
+
+    if let Some(hours) = station_config.window
+        && let Some(latest_time) = db.keys().next_back().copied()
+    {
+        db = db.split_off(&(latest_time - chrono::Duration::hours(hours)));
+    }
+    config.timings.parse(Modules::CurrentConditions, start);
+
+    Ok((db, None))
+}
+
+async fn station_conditions(x: &str, config: &Config) -> Result<StationReport, String> {
+    let default_station = StationConfig::default();
+    let station_config = config.weather.stations.get(x).unwrap_or(&default_station);
+
+    let (db, mirror) = station_history(x, station_config, config).await?;
+
+    let (latest_time, entry) = db
+        .last_key_value()
+        .ok_or(format!("{} did not have any data.", x))?;
+    let station = entry.station;
+
+    let start = Instant::now();
+
+    let local_time: DateTime<Local> = (*latest_time).into();
+
+    let name = station_config.name.as_ref().unwrap_or(&station.name);
+
+    let age = Utc::now() - *latest_time;
+    let stale = age > station_config.max_age(config.weather.max_age);
+
+    // old data shouldn't set off alerts
+    if !stale {
+        alerts::check_conditions(config, x, name, entry, station_layer(station_config));
+    }
+
+    if stale && config.weather.hide_stale {
+        return Ok(StationReport {
+            text: String::new(),
+            extra: String::new(),
+            row: None,
+            stale,
+        });
+    }
+
+    let mut time = format!("⌛{}", local_time.format("%I:%M %p"));
+    if stale {
+        time = format!("{time} ({} old)", span_str(age));
+    }
+
+    let via = match mirror {
+        Some(mirror) if config.verbosity >= 1 => format!(" via {mirror}"),
+        _ => String::new(),
+    };
+
+    let prelude = if stale {
+        format!("{name}: {}{time}{Reset}{via}", common::Style::warning())
+    } else {
+        format!("{name}: {time}{via}")
+    };
+
+    let parameters = &config.weather.selected.parameters;
+
+    // each layout only builds what it shows
+    let (text, row) = match config.weather.layout {
+        ConditionsLayout::Lines => (
+            station_line(&prelude, entry, parameters, station_config, &db, config)?,
+            None,
+        ),
+        ConditionsLayout::Table => (
+            String::new(),
+            Some(table_row(
+                name,
+                &format!("{time}{via}"),
+                stale,
+                entry,
+                station_config,
+                &db,
+                config,
+            )),
+        ),
+    };
+
+    let mut extra = sparklines(
+        &config.weather.selected.sparklines,
+        station_config,
+        &db,
+        config,
+    );
+    if station_config.range == WxerRange::Daily {
+        extra.push_str(&today_so_far(
+            &db,
+            station_config,
+            source_timezone(station_config)?,
+        ));
+    }
+    extra.push_str(&aviation::aviation_panel(&db, station_config));
+    config.timings.render(Modules::CurrentConditions, start);
+
+    Ok(StationReport {
+        text,
+        extra,
+        row,
+        stale,
+    })
 }
 
 async fn current_conditions_handler(config: &Config) -> Result<String, String> {
     let mut s = common::title("CURRENT CONDITIONS");
 
-    for x in config.weather.selected.sources.iter() {
-        let conditions = wxer_query(x, "hourly", config).await?;
+    let sources = &config.weather.selected.sources;
 
-        let data: WxerResponse = serde_json::from_str(&conditions).map_err(|e| e.to_string())?;
+    // every source is fetched at once, but shown in the configured order
+    let reports = join_all(sources.iter().map(|x| station_conditions(x, config))).await;
 
-        let db = data.data;
+    let mut stale = 0;
+    let mut failed = 0;
 
-        let latest = db
-            .last_key_value()
-            .ok_or(format!("{} did not have any data.", x))?;
+    let table = config.weather.layout == ConditionsLayout::Table;
+    let mut rows = vec![];
+    let mut extras = String::new();
+    let mut errors = String::new();
 
-        let entry: WxEntryStruct = latest
-            .1
-            .to_struct()
-            .map_err(|_| "Could not convert to struct.".to_string())?;
+    for (x, report) in sources.iter().zip(reports) {
+        match report {
+            Ok(r) => {
+                if r.stale {
+                    stale += 1;
+                }
 
-        let station = latest.1.station.clone();
+                match r.row {
+                    Some(row) if table => {
+                        if !r.extra.is_empty() {
+                            extras.push_str(&common::location_heading(&row.name));
+                            extras.push_str(&r.extra);
+                        }
+                        rows.push(row);
+                    }
+                    _ => {
+                        s.push_str(&r.text);
+                        s.push_str(&r.extra);
+                    }
+                }
+            }
+            Err(e) => {
+                failed += 1;
 
-        let indoor = station.name != "APT";
+                let name = config
+                    .weather
+                    .stations
+                    .get(x)
+                    .and_then(|st| st.name.as_ref())
+                    .unwrap_or(x);
 
-        let local_time: DateTime<Local> = (*latest.0).into();
+                let line = format!(
+                    "{name}: {}{}{Reset}\n",
+                    common::Style::error(),
+                    e.trim_end()
+                );
 
-        let name = if let Some(rename) = config.weather.rename_stations.get(x) {
-            rename
-        } else {
-            &station.name
-        };
+                // in place, unless it would break up the table
+                if table {
+                    errors.push_str(&line);
+                } else {
+                    s.push_str(&line);
+                }
+            }
+        }
+    }
 
-        let prelude = format!("{}: ⌛{}", name, local_time.format("%I:%M %p"));
-        let line = station_line(
-            &prelude,
-            &entry,
-            &config.weather.selected.parameters,
-            indoor,
-            &db,
-        )?;
-        s.push_str(&line)
+    if !rows.is_empty() {
+        s.push_str(&station_table(&rows, config));
+    }
+    s.push_str(&extras);
+    s.push_str(&errors);
+
+    if stale > 0 || failed > 0 {
+        let mut summary = vec![];
+
+        if failed > 0 {
+            summary.push(format!("{failed} failed"));
+        }
+        if stale > 0 && config.weather.hide_stale {
+            summary.push(format!("{stale} stale and hidden"));
+        } else if stale > 0 {
+            summary.push(format!("{stale} stale"));
+        }
+
+        s.push_str(&format!(
+            "{}{} of {} stations had problems: {}{Reset}\n",
+            common::Style::warning(),
+            stale + failed,
+            sources.len(),
+            summary.join(", ")
+        ));
     }
 
     Ok(s)
@@ -125,11 +326,9 @@ pub async fn current_conditions(config: &Config) {
     }
 
     match current_conditions_handler(config).await {
-        Ok(s) => {
-            println!("{}", s)
-        }
-        Err(e) => {
-            println!("{}{}", common::title("CURRENT CONDITIONS"), e)
-        }
+        Ok(s) => config.output.print(&s),
+        Err(e) => config
+            .output
+            .print(&format!("{}{}", common::title("CURRENT CONDITIONS"), e)),
     }
 }
@@ -0,0 +1,159 @@
+use crate::common;
+use crate::config::Config;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use serde::{Deserialize, Serialize};
+
+// WXER ------------------------------------------------------------------------
+
+// Remembers which addresses worked between runs, so a dead mirror listed first
+// doesn't cost a timeout every time.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct WxerHealth {
+    last_success: Option<String>,
+    #[serde(default)]
+    failures: HashMap<String, DateTime<Utc>>,
+}
+
+fn health_path() -> anyhow::Result<PathBuf> {
+    Ok(common::cache_dir()?.join("wxer_health.json"))
+}
+
+impl WxerHealth {
+    fn load() -> Self {
+        health_path()
+            .ok()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|t| serde_json::from_str(&t).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let path = health_path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, serde_json::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    // the last address that worked first, ones that have failed since last,
+    // otherwise the order from the config.
+    fn order(&self, addresses: &[String]) -> Vec<String> {
+        let mut ordered = addresses.to_vec();
+        ordered.sort_by_key(|a| {
+            (
+                self.last_success.as_ref() != Some(a),
+                self.failures.contains_key(a),
+            )
+        });
+        ordered
+    }
+
+    fn success(&mut self, addr: &str) {
+        self.last_success = Some(addr.to_string());
+        self.failures.remove(addr);
+    }
+
+    fn failure(&mut self, addr: &str) {
+        self.failures.insert(addr.to_string(), Utc::now());
+
+        if self.last_success.as_deref() == Some(addr) {
+            self.last_success = None;
+        }
+    }
+}
+
+// health is shared between every source being fetched at once.
+fn with_health<R, F: FnOnce(&mut WxerHealth) -> R>(config: &Config, f: F) -> Option<R> {
+    let mut health = config.wxer.health.lock().ok()?;
+    Some(f(health.get_or_insert_with(WxerHealth::load)))
+}
+
+async fn fetch(client: &reqwest::Client, url: String) -> Result<String, String> {
+    let r = client
+        .get(&url)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|err| format!("{url} - {:?}, {err}", err.status()))?;
+
+    if r.status().is_success() {
+        r.text().await.map_err(|e| format!("{url} - {e}"))
+    } else {
+        Err(format!(
+            "{url} - {}, {}",
+            r.status().as_u16(),
+            r.status().as_str()
+        ))
+    }
+}
+
+// returns the response and the address that served it.
+pub async fn wxer_query(
+    loc: &str,
+    time: &str,
+    config: &Config,
+) -> Result<(String, String), String> {
+    let addresses = with_health(config, |h| h.order(&config.wxer.addresses))
+        .unwrap_or(config.wxer.addresses.clone());
+
+    let client = reqwest::Client::new();
+
+    let attempt = |addr: &String| {
+        let url = format!("{addr}/{loc}/{time}.json");
+        let client = &client;
+        let addr = addr.clone();
+        async move { (addr, fetch(client, url).await) }
+    };
+
+    let mut attempts = FuturesUnordered::new();
+    let mut remaining = addresses.iter();
+
+    // racing starts every address at once, otherwise the next one is only
+    // tried once the previous has failed.
+    let concurrent = if config.wxer.race { addresses.len() } else { 1 };
+
+    for addr in remaining.by_ref().take(concurrent) {
+        attempts.push(attempt(addr));
+    }
+
+    let mut err_string = String::new();
+    let mut result = None;
+
+    while let Some((addr, r)) = attempts.next().await {
+        match r {
+            Ok(text) => {
+                with_health(config, |h| h.success(&addr));
+                result = Some((text, addr));
+                break;
+            }
+            Err(e) => {
+                with_health(config, |h| h.failure(&addr));
+                err_string.push_str(&e);
+                err_string.push('\n');
+
+                if let Some(addr) = remaining.next() {
+                    attempts.push(attempt(addr));
+                }
+            }
+        }
+    }
+
+    // the next run can do without it, so failing to save isn't an error
+    let _ = with_health(config, |h| h.save());
+
+    result.ok_or(format!(
+        "None of the addresses responded successfully!\n{err_string}"
+    ))
+}